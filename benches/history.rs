@@ -0,0 +1,104 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use doctor::changelog::git::{
+  get_commit_list_by_commit_range, tag_history, ReleaseOptions, WalkOptions,
+};
+use doctor::changelog::history::package_full_commits;
+use git2::{Oid, Repository, Signature, Time};
+use tempfile::TempDir;
+
+const PACKAGES: [&str; 6] = [
+  "@ant-design/pro-card",
+  "@ant-design/pro-field",
+  "@ant-design/pro-form",
+  "@ant-design/pro-layout",
+  "@ant-design/pro-table",
+  "@ant-design/pro-utils",
+];
+const RELEASES: usize = 40;
+const COMMITS_PER_RELEASE: usize = 20;
+
+/// Generate a monorepo history: every release merges a feature branch into
+/// master, and tags a version of each package on the merge commit.
+fn generate(dir: &TempDir) -> Repository {
+  let repo = Repository::init(dir.path()).unwrap();
+  let mut time = 1_600_000_000;
+  let mut commit = |parents: &[Oid], path: &str| -> Oid {
+    time += 60;
+    let signature = Signature::new("doctor", "doctor@example.com", &Time::new(time, 0)).unwrap();
+    let parents: Vec<_> = parents
+      .iter()
+      .map(|id| repo.find_commit(*id).unwrap())
+      .collect();
+    let mut builder = repo.treebuilder(None).unwrap();
+    let blob = repo.blob(time.to_string().as_bytes()).unwrap();
+    builder.insert(path, blob, 0o100644).unwrap();
+    let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+    let parent_refs: Vec<_> = parents.iter().collect();
+    repo
+      .commit(
+        None,
+        &signature,
+        &signature,
+        &format!("feat: change {}", path),
+        &tree,
+        &parent_refs,
+      )
+      .unwrap()
+  };
+
+  let mut master = commit(&[], "README.md");
+  let mut tags = vec![];
+  for release in 0..RELEASES {
+    let mut branch = master;
+    for index in 0..COMMITS_PER_RELEASE {
+      let package = PACKAGES[index % PACKAGES.len()];
+      let path = format!("{}.txt", package.trim_start_matches("@ant-design/pro-"));
+      if index % 2 == 0 {
+        branch = commit(&[branch], &path);
+      } else {
+        master = commit(&[master], &path);
+      }
+    }
+    master = commit(&[master, branch], "merge.txt");
+    tags.push((release, master));
+  }
+
+  for (release, id) in tags {
+    let object = repo.find_object(id, None).unwrap();
+    for package in PACKAGES.iter() {
+      repo
+        .tag_lightweight(&format!("{}@1.{}.0", package, release), &object, false)
+        .unwrap();
+    }
+  }
+  repo
+}
+
+fn bench(c: &mut Criterion) {
+  let dir = TempDir::new().unwrap();
+  let repo = generate(&dir);
+  let package_names: Vec<String> = PACKAGES.iter().map(|name| name.to_string()).collect();
+  let release_options = ReleaseOptions::default();
+  let options = WalkOptions::default();
+
+  let mut group = c.benchmark_group("full_history");
+  group.sample_size(10);
+  group.bench_function("revwalk_per_range", |b| {
+    b.iter(|| {
+      for package_name in PACKAGES.iter() {
+        for release in tag_history(&repo, package_name, &release_options).unwrap() {
+          get_commit_list_by_commit_range(&repo, release.range, &options).unwrap();
+        }
+      }
+    })
+  });
+  group.bench_function("single_walk", |b| {
+    b.iter(|| {
+      package_full_commits(&repo, &package_names, &release_options, |_| options.clone()).unwrap()
+    })
+  });
+  group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
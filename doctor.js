@@ -1,3 +1,91 @@
-﻿#!/usr/bin/env node
-
-console.log("run");
+#!/usr/bin/env node
+
+const { genRangeChangelog, genUnreleasedChangelog, setLogger } = require("./index.js");
+
+const HELP = `用法：
+  doctor changelog <from> [to]   生成两个 ref 之间的 changelog，to 默认为 HEAD
+  doctor unreleased [head]       生成 head 到最新的 tag 之间还没有发布的 changelog
+
+选项：
+  --repo <path>                  仓库的地址，默认为当前目录
+  --commit-match <mode>          commit 归属到包的方式，scope、path 或者 scope-or-path
+  --first-parent                 只沿着 merge commit 的第一个 parent 遍历
+  --no-merges                    跳过 merge commit
+  --diff-stats                   输出修改的文件数和增删的行数
+  --date-format <format>         版本日期的格式，默认为 %Y-%m-%d
+  --timezone <timezone>          版本日期的时区，utc、local、original 或者 +08:00
+  --tag-summary                  输出 annotated tag 的注释
+  --avatars                      html 格式中显示贡献者的 github 头像
+  --pr-details                   获取 pr 的标题、label 和合并的人，label 会用来分组
+  --format <format>              输出的格式，markdown、keep-a-changelog、json、yaml 或者 html
+  --template <path>              自定义的 handlebars 模板文件，相对路径基于仓库的目录
+  --locale <locale>              输出的语言，zh-CN 或者 en-US，等同于环境变量 DOCTOR_LOCALE
+  --quiet                        只输出警告和错误
+  --verbose                      输出调试信息
+  --json-logs                    每条日志输出为一行 json
+
+没有传入的选项会使用仓库中 doctor.config.json、.doctorrc 或者 package.json#doctor 的配置`;
+
+const BOOLEAN_OPTIONS = [
+  "firstParent",
+  "noMerges",
+  "diffStats",
+  "tagSummary",
+  "avatars",
+  "prDetails",
+  "quiet",
+  "verbose",
+  "jsonLogs",
+  "help",
+];
+
+/* 把参数分成位置参数和选项，选项的名字转成 camelCase */
+function parseArgs(argv) {
+  const positional = [];
+  const options = {};
+  for (let i = 0; i < argv.length; i++) {
+    const arg = argv[i];
+    if (!arg.startsWith("--")) {
+      positional.push(arg);
+      continue;
+    }
+    const name = arg
+      .slice(2)
+      .replace(/-([a-z])/g, (_, char) => char.toUpperCase());
+    if (BOOLEAN_OPTIONS.includes(name)) {
+      options[name] = true;
+    } else {
+      options[name] = argv[++i];
+    }
+  }
+  return { positional, options };
+}
+
+function main() {
+  const { positional, options } = parseArgs(process.argv.slice(2));
+  const [command, ...args] = positional;
+  const {
+    repo = process.cwd(),
+    help,
+    locale,
+    quiet,
+    verbose,
+    jsonLogs,
+    ...changelogOptions
+  } = options;
+  if (locale) {
+    process.env.DOCTOR_LOCALE = locale;
+  }
+  setLogger({ quiet, verbose, jsonLogs });
+
+  if (command === "changelog" && args.length > 0) {
+    console.log(genRangeChangelog(repo, args[0], args[1], changelogOptions));
+  } else if (command === "unreleased") {
+    console.log(genUnreleasedChangelog(repo, args[0], changelogOptions));
+  } else {
+    console.log(HELP);
+    process.exitCode = help ? 0 : 1;
+  }
+}
+
+main();
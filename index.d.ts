@@ -3,12 +3,150 @@
 
 /* auto-generated by NAPI-RS */
 
+/** changelog 相关操作的配置，没有传入的会使用 doctor.config.json 中 changelog 的配置 */
+export interface ChangelogOptions {
+  /** commit 归属到包的方式，scope、path 或者 scope-or-path，默认为 scope */
+  commitMatch?: string;
+  /** 只沿着 merge commit 的第一个 parent 遍历 */
+  firstParent?: boolean;
+  /** 跳过 merge commit */
+  noMerges?: boolean;
+  /**
+   * 在每个版本中输出修改的文件数、增删的行数和修改最多的文件
+   * 按照目录匹配时只统计包的目录
+   */
+  diffStats?: boolean;
+  /**
+   * 预发布版本的处理方式，include、exclude 或者 roll-up，默认为 include
+   * roll-up 会在稳定版本发布之后把预发布版本的修改合并到稳定版本中
+   */
+  prerelease?: string;
+  /** latest 使用最新的稳定版本（stable）还是最新的任意版本（any），默认为 any */
+  latest?: string;
+  /** git tag 的命名方式，比如 v{version}、{name}-v{version}，默认为 {name}@{version} */
+  tagPattern?: string;
+  /** 版本日期的格式，使用 strftime 的语法，默认为 %Y-%m-%d */
+  dateFormat?: string;
+  /** 版本日期的时区，utc、local、original（tag 中记录的时区）或者 +08:00 这样的偏移，默认为 utc */
+  timezone?: string;
+  /** 在版本的标题下面输出 annotated tag 的注释 */
+  tagSummary?: boolean;
+  /** html 格式中在贡献者前面显示 github 的头像 */
+  avatars?: boolean;
+  /** 获取 pr 的标题、label 和合并的人，标题会代替 commit 信息输出，label 会用来分组，每个 pr 都会请求一次 */
+  prDetails?: boolean;
+  /** 输出的格式，markdown、keep-a-changelog、json、yaml 或者 html，默认为 markdown */
+  format?: string;
+  /**
+   * 自定义的 handlebars 模板文件，相对路径基于仓库的目录，设置之后会忽略 format
+   * 文件名为 changelog.html.hbs 这样时，生成的文件会使用 html 后缀
+   */
+  template?: string;
+}
 export function genChangelogs(
   repo: string,
-  changelogPath?: string | undefined | null
+  changelogPath?: string | undefined | null,
+  options?: ChangelogOptions | undefined | null
 ): void;
 export function genAllChangelogs(
   repo: string,
-  changelogPath?: string | undefined | null
+  changelogPath?: string | undefined | null,
+  options?: ChangelogOptions | undefined | null
+): void;
+/**
+ * 生成 head 到最新的 tag 之间还没有发布的 changelog，返回配置的格式，默认为 markdown，可以用于发布 PR 的描述
+ * head 默认为 HEAD，也可以是任意的分支名、tag 或者 commit
+ */
+export function genUnreleasedChangelog(
+  repo: string,
+  head?: string | undefined | null,
+  options?: ChangelogOptions | undefined | null
+): string;
+/**
+ * 生成两个 ref 之间的 changelog，返回配置的格式，默认为 markdown，ref 可以是分支名、tag 或者 commit
+ * to 默认为 HEAD，标题中会带上 github 的 compare 链接
+ */
+export function genRangeChangelog(
+  repo: string,
+  from: string,
+  to?: string | undefined | null,
+  options?: ChangelogOptions | undefined | null
+): string;
+/** dist-tag 相关操作的配置，没有传入的会从配置文件和环境变量读取 */
+export interface NpmOptions {
+  /** 一次性密码，等同于 NPM_CONFIG_OTP */
+  otp?: string;
+  /** base32 格式的 TOTP 密钥，用来自动生成 otp */
+  totpSecret?: string;
+  /** 非交互模式，不会读取 stdin，CI 环境下默认开启 */
+  nonInteractive?: boolean;
+  /** 跳过 dist-tag 变更的确认 */
+  yes?: boolean;
+  /** dist-tag 的执行方式，npm 或者 registry，默认为 npm */
+  backend?: string;
+  /** git tag 的命名方式，比如 v{version}、{name}-v{version}，默认为 {name}@{version} */
+  tagPattern?: string;
+}
+export function checkPublish(
+  repo: string,
+  tag?: string | undefined | null,
+  options?: NpmOptions | undefined | null
+): void;
+export function promoteDistTag(
+  repo: string,
+  from: string,
+  to?: string | undefined | null,
+  options?: NpmOptions | undefined | null
+): void;
+export function removeDistTag(
+  repo: string,
+  tag: string,
+  options?: NpmOptions | undefined | null
+): void;
+export function rollbackLatest(
+  repo: string,
+  options?: NpmOptions | undefined | null
+): void;
+export interface PreflightIssue {
+  rule: string;
+  severity: "error" | "warning";
+  message: string;
+  field: Array<string>;
+}
+export interface PackageReport {
+  name: string;
+  version: string;
+  path: string;
+  issues: Array<PreflightIssue>;
+}
+export interface PreflightReport {
+  packages: Array<PackageReport>;
+}
+/** 发布之前检查所有的包，返回结构化的检查结果 */
+export function preflight(repo: string): PreflightReport;
+/** 日志的配置 */
+export interface LogOptions {
+  /** 只输出警告和错误 */
+  quiet?: boolean;
+  /** 输出调试信息，比如使用的配置文件和每个版本的 commit 数量 */
+  verbose?: boolean;
+  /** 每条日志输出为一行 json，包含 level、target、message 和 timestamp */
+  jsonLogs?: boolean;
+}
+export interface LogEvent {
+  level: "error" | "warn" | "info" | "debug" | "trace";
+  /** 输出日志的模块，比如 doctor::npm */
+  target: string;
+  message: string;
+  /** RFC 3339 格式的时间 */
+  timestamp: string;
+}
+/**
+ * 设置日志的级别和格式，对之后所有的调用生效
+ * 传入 callback 之后日志不再输出到 stderr，而是同步调用 callback
+ * 不传入 callback 时恢复输出到 stderr
+ */
+export function setLogger(
+  options?: LogOptions | undefined | null,
+  callback?: ((event: LogEvent) => void) | undefined | null
 ): void;
-export function checkPublish(repo: string): void;
@@ -321,8 +321,26 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`);
 }
 
-const { genChangelogs, genAllChangelogs, checkPublish } = nativeBinding;
+const {
+  genChangelogs,
+  genAllChangelogs,
+  genUnreleasedChangelog,
+  genRangeChangelog,
+  checkPublish,
+  promoteDistTag,
+  removeDistTag,
+  rollbackLatest,
+  preflight,
+  setLogger,
+} = nativeBinding;
 
 module.exports.genChangelogs = genChangelogs;
 module.exports.genAllChangelogs = genAllChangelogs;
+module.exports.genUnreleasedChangelog = genUnreleasedChangelog;
+module.exports.genRangeChangelog = genRangeChangelog;
 module.exports.checkPublish = checkPublish;
+module.exports.promoteDistTag = promoteDistTag;
+module.exports.removeDistTag = removeDistTag;
+module.exports.rollbackLatest = rollbackLatest;
+module.exports.preflight = preflight;
+module.exports.setLogger = setLogger;
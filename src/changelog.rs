@@ -1,24 +1,121 @@
-﻿pub mod git;
+﻿pub mod filter;
+pub mod git;
+pub mod history;
+pub mod issues;
+pub mod render;
+pub mod template;
 use git2::Repository;
+use log::{debug, warn};
 use regex::Regex;
 use reqwest::Client;
-use serde::Deserialize;
-use std::{collections::HashMap, env, ops::Index};
+use serde::{de::IgnoredAny, Deserialize};
+use std::{
+  collections::{HashMap, HashSet},
+  env, fs,
+  ops::Index,
+  path::Path,
+  str::FromStr,
+};
 
-use self::git::{full_commits, latest_commits, Commit};
+use self::filter::{parse_revert, same_hash, CommitFilters};
+use self::git::{
+  first_commits, latest_commits, package_ref_range_commits, unreleased_commits, Commit, DiffStats,
+  ReleaseOptions, TagAndCommit, WalkOptions,
+};
+use self::history::package_full_commits;
+use self::issues::{merge_issue_refs, parse_issue_refs, IssueKind, IssueLink, IssueRef, JiraKeys};
+use self::render::{
+  merge_contributors, CommitTypes, Contributor, Entry, Html, KeepAChangelog, OutputFormat,
+  PackageNotes, PullRequest, ReleaseNotes, Renderer,
+};
+use self::template::Template;
+use crate::i18n::t;
+use crate::npm::workspace::find_packages;
+use crate::ErrorKind;
+
+/// commit 归属到哪个包的判断方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitMatch {
+  /// 根据 commit 信息中的 scope，比如 fix(table)
+  Scope,
+  /// 根据 commit 修改的文件是否在包的目录下
+  Path,
+  /// scope 或者修改的文件，满足一个就可以
+  ScopeOrPath,
+}
+
+impl FromStr for CommitMatch {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<CommitMatch> {
+    match s {
+      "scope" => Ok(CommitMatch::Scope),
+      "path" => Ok(CommitMatch::Path),
+      "scope-or-path" => Ok(CommitMatch::ScopeOrPath),
+      _ => Err(ErrorKind::Config(t!("changelog.commit_match_invalid", s)).into()),
+    }
+  }
+}
+
+/// 代码托管平台的配置，没有设置的从 origin 推断为 github 的仓库
+#[derive(Debug, Clone, Default)]
+pub struct Forge {
+  /// 仓库的网页地址，比如 https://github.com/ant-design/pro-components，没有的话通过 api 获取
+  pub html_url: Option<String>,
+  /// api 的地址，默认为 https://api.github.com
+  pub api_url: Option<String>,
+  /// 仓库的名字，比如 ant-design/pro-components，默认从 origin 的地址中解析
+  pub repo_name: Option<String>,
+}
+
+/// github 的 api 地址
+const GITHUB_API_URL: &str = "https://api.github.com";
 
 pub struct Changelogs {
   repo: Repository,
   author_github_map: HashMap<String, String>,
   client: Client,
   github_html_url: String,
+  /// 托管平台的 api 地址
+  api_url: String,
   repo_name: String,
-}
-
-#[derive(Debug)]
-pub struct MARKDOWN {
-  pub package: String,
-  pub content: String,
+  commit_match: CommitMatch,
+  /// 遍历 commit 的配置，collect_paths 会根据 commit_match 自动设置
+  walk_options: WalkOptions,
+  /// 预发布版本的处理方式，以及 latest 是否包含预发布版本
+  release_options: ReleaseOptions,
+  /// 是否在标题下面输出 tag 的注释
+  tag_summary: bool,
+  /// 包所在的目录，设置之后包的路径是 <packages_dir>/<package>/
+  packages_dir: Option<String>,
+  /// workspace 中找到的包名和包相对仓库根目录的路径，比如 packages/table/
+  package_dirs: Vec<(String, String)>,
+  /// 包名的前缀，包名是 <package_prefix><package>，默认为 workspace 中所有包名共同的前缀
+  package_prefix: String,
+  /// 需要生成 changelog 的包，第一个包会带上版本的标题，默认为 workspace 中所有的包
+  package_list: Vec<String>,
+  /// keep-a-changelog 中 commit 的类型对应的分组
+  commit_types: CommitTypes,
+  /// 输出的格式，默认为 markdown
+  format: OutputFormat,
+  /// 自定义的模板，设置之后会代替 format 输出
+  template: Option<Template>,
+  /// 生成 changelog 之前过滤掉的 commit
+  filters: CommitFilters,
+  /// 每个作者在所有 tag 的历史中的第一个 commit，用来判断首次贡献，第一次用到时才查找
+  first_commits: Option<HashMap<String, String>>,
+  /// html 中是否显示贡献者的头像
+  avatars: bool,
+  /// jira 这样的 issue 的 key 和链接，没有设置时不识别
+  jira: Option<JiraKeys>,
+  /// github 的 issue 或者 pr 的链接，key 为 <repo>#<number>，每个 issue 只请求一次
+  issue_links: HashMap<String, IssueLink>,
+  /// 是否获取 pr 的标题、label 和合并的人，开启后每个 pr 都会请求一次
+  pr_details: bool,
+  /// pr 的编号 -> 获取到的 pr，只在 pr_details 开启时保存
+  pull_requests: HashMap<String, GithubPull>,
+  /// pr 的编号 -> pr 的描述中关闭的 issue，请求过 pr 时才有，开启 pr_details 时每个 pr 都会请求
+  pull_request_refs: HashMap<String, Vec<IssueRef>>,
 }
 
 #[derive(Deserialize)]
@@ -26,9 +123,30 @@ struct GithubUser {
   login: String,
 }
 
+#[derive(Deserialize)]
+struct GithubLabel {
+  name: String,
+}
+
 #[derive(Deserialize)]
 struct GithubPull {
   user: GithubUser,
+  #[serde(default)]
+  title: Option<String>,
+  #[serde(default)]
+  labels: Vec<GithubLabel>,
+  #[serde(default)]
+  merged_by: Option<GithubUser>,
+  /// pr 的描述，其中关闭的 issue 会和 commit 中的合并
+  #[serde(default)]
+  body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubIssue {
+  html_url: String,
+  /// 只有 pr 才有这个字段
+  pull_request: Option<IgnoredAny>,
 }
 
 #[derive(Deserialize)]
@@ -36,39 +154,76 @@ struct GithubRepo {
   html_url: String,
 }
 
+/// 根目录的 package.json，只需要包名
+#[derive(Deserialize)]
+struct RootPackage {
+  name: Option<String>,
+}
+
 impl Changelogs {
   pub fn gen_change_log_by_commit_list(
     &mut self,
     commit_list: Vec<Commit>,
     package: &str,
-  ) -> crate::Result<Vec<String>> {
-    let mut changelog_list: Vec<String> = vec![];
+  ) -> crate::Result<Vec<Entry>> {
+    let mut changelog_list: Vec<Entry> = vec![];
+    // 版本中所有的 commit，回滚版本内的 commit 时不算作回滚之前的版本
+    let release_hashes: Vec<String> = commit_list
+      .iter()
+      .map(|commit| commit.hash().to_owned())
+      .collect();
+
+    let (commit_list, excluded) = self.filters.split(commit_list);
+    for (commit, filter) in &excluded {
+      debug!(
+        "{}",
+        t!("changelog.commit_excluded", &commit.hash()[..7], filter)
+      );
+    }
 
     let mut commit_hash_map: HashMap<String, bool> = HashMap::new();
 
     for commit in commit_list {
-      let message = commit.message().split("\n").nth(0).expect("信息转化失败");
+      let message = commit
+        .message()
+        .split("\n")
+        .nth(0)
+        .unwrap_or_else(|| panic!("{}", t!("changelog.message_failed")));
       let hash = commit.hash().to_string();
 
-      let re = Regex::new(r"[fix|feat]\(([0-9a-zA-Z_]*)\)").expect("正则表达式转化失败");
+      let re = Regex::new(r"[fix|feat]\(([0-9a-zA-Z_]*)\)")
+        .unwrap_or_else(|_| panic!("{}", t!("changelog.regex_failed")));
 
       let mut need_insert_message = false;
 
-      if re.is_match(message) {
-        if re
+      if self.commit_match != CommitMatch::Path
+        && re.is_match(message)
+        && re
           .captures(message)
-          .expect("正则表达式转化失败")
+          .unwrap_or_else(|| panic!("{}", t!("changelog.regex_failed")))
           .index(1)
           .to_lowercase()
           .eq(package)
-        {
-          need_insert_message = true
-        }
+      {
+        need_insert_message = true
+      }
+
+      if self.commit_match != CommitMatch::Scope && !need_insert_message {
+        let package_path = self.package_path(package);
+        need_insert_message = commit
+          .paths()
+          .iter()
+          .any(|path| path.starts_with(&package_path));
       }
 
       if need_insert_message && !commit_hash_map.get(&hash).is_some() {
-        let md_message = self.get_md_message(&commit);
-        changelog_list.insert(changelog_list.len(), md_message.clone());
+        let mut entry = self.get_entry(&commit);
+        if let Some(reverted) = &entry.reverts {
+          if release_hashes.iter().any(|hash| same_hash(hash, reverted)) {
+            entry.reverts = None;
+          }
+        }
+        changelog_list.push(entry);
 
         commit_hash_map.insert(hash, true);
       }
@@ -76,127 +231,325 @@ impl Changelogs {
 
     Ok(changelog_list)
   }
-  pub fn gen_change_log_to_md(&mut self, change_logs: Vec<String>) -> String {
-    let mut md_file_content: String = "".to_owned();
-
-    for changelog in change_logs {
-      // 格式化成这个样子
-      //  * feat(layout): mix support headerContent render [@chenshuai2144](https://github.com/chenshuai2144)
-      md_file_content.push_str(&("* ".to_owned() + &changelog + "\n"));
+  /* 遍历 commit 时的配置，按照目录匹配时需要收集修改的文件，diff 统计也只统计包的目录 */
+  fn walk_options(&self, package: &str) -> WalkOptions {
+    let path_scoped = self.commit_match != CommitMatch::Scope;
+    WalkOptions {
+      collect_paths: path_scoped,
+      // 根目录的包统计整个仓库
+      stats_path: if path_scoped {
+        Some(self.package_path(package)).filter(|path| !path.is_empty())
+      } else {
+        None
+      },
+      ..self.walk_options.clone()
     }
+  }
 
-    md_file_content
+  /**
+   * 包相对仓库根目录的路径，以 / 结尾，根目录的包为空
+   * 配置了 packages_dir 时使用 <packages_dir>/<package>/，否则使用 workspace 中同名的包的目录
+   */
+  fn package_path(&self, package: &str) -> String {
+    if let Some(packages_dir) = &self.packages_dir {
+      return format!("{}/{}/", packages_dir.trim_end_matches('/'), package);
+    }
+    let name = self.package_prefix.clone() + package;
+    self
+      .package_dirs
+      .iter()
+      .find(|(package_name, _)| *package_name == name)
+      .map(|(_, dir)| dir.clone())
+      .unwrap_or_else(|| format!("packages/{}/", package))
   }
 
   /**
    * 获取所有的changelog
    * 会遍历所有的标签
    */
-  pub fn get_all_change_log_list(&mut self) -> Vec<MARKDOWN> {
-    let mut md_packages: Vec<MARKDOWN> = vec![];
-    let package_list = [
-      "components",
-      "utils",
-      "layout",
-      "form",
-      "list",
-      "table",
-      "field",
-      "card",
-      "descriptions",
-    ];
-    for package in package_list {
-      let mut package_md: Vec<String> = vec![];
-      let commit_and_tag_list =
-        full_commits(&self.repo, &("@ant-design/pro-".to_owned() + package))
-          .expect("获取commit失败");
-
-      for commit_and_tag in commit_and_tag_list {
-        let change_logs = self
-          .gen_change_log_by_commit_list(commit_and_tag.commit_list, package)
-          .expect("生成changelog 失败，请重试");
-
-        let md_file_content = self.gen_change_log_to_md(change_logs);
-
-        let tag = commit_and_tag.tag;
-        if package == "components" {
-          package_md.push(("\n## ".to_owned() + tag.name.as_str() + "\n\n").to_string());
-          package_md.push(format!("`{date_time}`\n\n", date_time = tag.date_time).to_string());
-        }
+  pub fn get_all_change_log_list(&mut self) -> Vec<PackageNotes> {
+    let mut package_notes_list: Vec<PackageNotes> = vec![];
+    let package_names: Vec<String> = self
+      .package_list
+      .iter()
+      .map(|package| self.package_prefix.clone() + package)
+      .collect();
+    // 所有包共用一次 history 的遍历
+    let package_commit_list = package_full_commits(
+      &self.repo,
+      &package_names,
+      &self.release_options,
+      |package_name| self.walk_options(package_name.trim_start_matches(&self.package_prefix)),
+    )
+    .unwrap_or_else(|_| panic!("{}", t!("changelog.commits_failed")));
 
-        package_md.insert(package_md.len(), md_file_content);
-      }
+    for (package, commit_and_tag_list) in self.package_list.clone().iter().zip(package_commit_list)
+    {
+      let releases = commit_and_tag_list
+        .into_iter()
+        .map(|commit_and_tag| {
+          let title = commit_and_tag.tag.name.clone();
+          self.gen_release_notes(package, &title, commit_and_tag)
+        })
+        .collect();
 
-      md_packages.insert(
-        md_packages.len(),
-        MARKDOWN {
-          package: package.to_owned(),
-          content: package_md.join(""),
-        },
-      )
+      package_notes_list.push(PackageNotes {
+        package: package.to_owned(),
+        releases,
+      })
     }
-    md_packages
+    package_notes_list
   }
 
   // 获取所有包的change log，会循环一下
-  pub fn get_change_log_list(&mut self) -> Vec<MARKDOWN> {
-    let mut md_packages: Vec<MARKDOWN> = vec![];
-    let package_list = [
-      "components",
-      "utils",
-      "layout",
-      "form",
-      "list",
-      "table",
-      "field",
-      "card",
-      "descriptions",
-    ];
-
-    for package in package_list {
-      let (tag, commit_list) =
-        latest_commits(&self.repo, &("@ant-design/pro-".to_owned() + package))
-          .expect("获取包名失败");
-
-      let change_logs = self
-        .gen_change_log_by_commit_list(commit_list, package)
-        .expect("生成changelog 失败，请重试");
-
-      let mut md_file_content: String = "".to_owned();
-      if package == "components" {
-        md_file_content.push_str(&("## ".to_owned() + tag.name.as_str() + "\n\n"));
-        md_file_content.push_str(format!("`{date_time}`\n\n", date_time = tag.date_time).as_str());
+  pub fn get_change_log_list(&mut self) -> Vec<PackageNotes> {
+    let mut package_notes_list: Vec<PackageNotes> = vec![];
+    for package in self.package_list.clone().iter() {
+      let tag_and_commit = latest_commits(
+        &self.repo,
+        &(self.package_prefix.clone() + package),
+        &self.release_options,
+        &self.walk_options(package),
+      )
+      .unwrap_or_else(|_| panic!("{}", t!("changelog.package_failed")));
+
+      let title = tag_and_commit.tag.name.clone();
+      package_notes_list.push(self.gen_package_notes(package, &title, tag_and_commit));
+    }
+
+    package_notes_list
+  }
+
+  /**
+   * 获取所有包在 head 和最新的 tag 之间还没有发布的 change log
+   * head 可以是任意的 ref，比如 HEAD 或者分支名
+   */
+  pub fn get_unreleased_change_log_list(&mut self, head: &str) -> crate::Result<Vec<PackageNotes>> {
+    let mut package_notes_list: Vec<PackageNotes> = vec![];
+
+    for package in self.package_list.clone().iter() {
+      let tag_and_commit = unreleased_commits(
+        &self.repo,
+        &(self.package_prefix.clone() + package),
+        head,
+        &self.release_options,
+        &self.walk_options(package),
+      )?;
+      let title = tag_and_commit.tag.name.clone();
+      package_notes_list.push(self.gen_package_notes(package, &title, tag_and_commit));
+    }
+
+    Ok(package_notes_list)
+  }
+
+  /**
+   * 获取所有包在两个 ref 之间的 change log，ref 可以是分支名、tag 或者 commit
+   * 标题会带上 github 的 compare 链接，所有包共用一次 commit 的遍历
+   */
+  pub fn get_ref_range_change_log_list(
+    &mut self,
+    from: &str,
+    to: &str,
+  ) -> crate::Result<Vec<PackageNotes>> {
+    let mut package_notes_list: Vec<PackageNotes> = vec![];
+    let title = format!("{from}...{to}", from = from, to = to);
+    let compare_url = self.get_compare_url(from, to);
+    let package_names: Vec<String> = self
+      .package_list
+      .iter()
+      .map(|package| self.package_prefix.clone() + package)
+      .collect();
+    let package_commit_list = package_ref_range_commits(
+      &self.repo,
+      from,
+      to,
+      &package_names,
+      &self.release_options,
+      |package_name| {
+        let package = package_name.strip_prefix(&self.package_prefix);
+        self.walk_options(package.unwrap_or(package_name))
+      },
+    )?;
+
+    for (package, tag_and_commit) in self.package_list.clone().iter().zip(package_commit_list) {
+      let mut package_notes = self.gen_package_notes(package, &title, tag_and_commit);
+      package_notes.releases[0].compare_url = Some(compare_url.clone());
+      package_notes_list.push(package_notes);
+    }
+
+    Ok(package_notes_list)
+  }
+
+  /* github 上对比两个 ref 的链接 */
+  pub fn get_compare_url(&self, from: &str, to: &str) -> String {
+    format!(
+      "{html_url}/compare/{from}...{to}",
+      html_url = self.github_html_url,
+      from = from,
+      to = to
+    )
+  }
+
+  /* 按照配置的模板或者格式输出所有包的 change log */
+  pub fn render(&self, package_notes_list: &[PackageNotes]) -> crate::Result<String> {
+    match (&self.template, self.format) {
+      (Some(template), _) => template.render(package_notes_list),
+      (None, OutputFormat::KeepAChangelog) => {
+        KeepAChangelog::new(self.commit_types.clone()).render(package_notes_list)
       }
+      (None, OutputFormat::Html) => Html::default()
+        .with_avatars(self.avatars)
+        .render(package_notes_list),
+      (None, format) => format.renderer().render(package_notes_list),
+    }
+  }
 
-      md_file_content.push_str(self.gen_change_log_to_md(change_logs).as_str());
+  /* 输出文件的后缀 */
+  pub fn extension(&self) -> &str {
+    match &self.template {
+      Some(template) => template.extension(),
+      None => self.format.extension(),
+    }
+  }
 
-      md_packages.insert(
-        md_packages.len(),
-        MARKDOWN {
-          package: package.to_owned(),
-          content: md_file_content,
-        },
-      );
+  /* 只有一个版本的包，比如 latest、unreleased 和两个 ref 之间的 change log */
+  fn gen_package_notes(
+    &mut self,
+    package: &str,
+    title: &str,
+    tag_and_commit: TagAndCommit,
+  ) -> PackageNotes {
+    PackageNotes {
+      package: package.to_owned(),
+      releases: vec![self.gen_release_notes(package, title, tag_and_commit)],
+    }
+  }
+
+  /* 生成一个包的一个版本的数据，所有的格式都是根据这个数据来输出的 */
+  fn gen_release_notes(
+    &mut self,
+    package: &str,
+    title: &str,
+    tag_and_commit: TagAndCommit,
+  ) -> ReleaseNotes {
+    debug!(
+      "{}",
+      t!(
+        "changelog.release_commits",
+        tag_and_commit.tag.name,
+        tag_and_commit.commit_list.len()
+      )
+    );
+    let stats = self.gen_stats(package, tag_and_commit.stats);
+    // commit 会在生成条目时被消耗，先记下每个 commit 的作者
+    let commit_authors: HashMap<String, String> = tag_and_commit
+      .commit_list
+      .iter()
+      .filter_map(|commit| Some((commit.hash().to_owned(), commit.author().clone()?)))
+      .collect();
+    let entries = self
+      .gen_change_log_by_commit_list(tag_and_commit.commit_list, package)
+      .unwrap_or_else(|_| panic!("{}", t!("changelog.generate_failed")));
+    let tag = tag_and_commit.tag;
+
+    ReleaseNotes {
+      title: title.to_owned(),
+      compare_url: None,
+      // tag 的注释，放在版本的条目上面
+      summary: tag.summary.filter(|_| self.tag_summary),
+      tag: tag.name,
+      date: tag.date_time,
+      contributors: self.gen_contributors(&entries, &commit_authors),
+      entries,
+      stats,
     }
+  }
+
+  /**
+   * 版本的贡献者，通过 pr 找到了 github id 的显示 id，否则显示 git 的作者
+   * 作者在所有 tag 的历史中的第一个 commit 出现在这个版本的条目中时标记为首次贡献，
+   * 第一个 commit 被过滤掉或者属于其他的包时不会标记
+   */
+  fn gen_contributors(
+    &mut self,
+    entries: &[Entry],
+    commit_authors: &HashMap<String, String>,
+  ) -> Vec<Contributor> {
+    let first_commits = self.first_commits();
+    let newcomers: HashSet<String> = entries
+      .iter()
+      .filter_map(|entry| {
+        let author = commit_authors.get(&entry.hash)?;
+        Some(author.clone()).filter(|author| first_commits.get(author) == Some(&entry.hash))
+      })
+      .collect();
+
+    let contributors: Vec<Contributor> = entries
+      .iter()
+      .filter_map(|entry| commit_authors.get(&entry.hash))
+      .map(|author| {
+        let contributor = match self.author_github_map.get(author) {
+          Some(login) => Contributor::github(login),
+          None => Contributor::git(author),
+        };
+        Contributor {
+          first_time: newcomers.contains(author),
+          ..contributor
+        }
+      })
+      .collect();
+    merge_contributors(contributors)
+  }
+
+  /* 每个作者的第一个 commit，只遍历一次历史，失败时不标记首次贡献 */
+  fn first_commits(&mut self) -> &HashMap<String, String> {
+    let repo = &self.repo;
+    self.first_commits.get_or_insert_with(|| {
+      first_commits(repo).unwrap_or_else(|err| {
+        warn!("{}", t!("changelog.first_commits_failed", err));
+        HashMap::new()
+      })
+    })
+  }
 
-    md_packages
+  /**
+   * diff 的统计，按照 scope 匹配的时候统计的是整个仓库，只在第一个包中输出一次
+   */
+  fn gen_stats(&self, package: &str, stats: Option<DiffStats>) -> Option<DiffStats> {
+    let stats = stats.filter(|stats| stats.files_changed > 0)?;
+    if self.commit_match == CommitMatch::Scope
+      && self.package_list.first().map(String::as_str) != Some(package)
+    {
+      return None;
+    }
+    Some(stats)
   }
 
-  pub fn get_md_message(&mut self, commit: &Commit) -> String {
+  /* commit 在 change log 中的条目，带有 pr 的会通过 pr 找到作者的 github id */
+  pub fn get_entry(&mut self, commit: &Commit) -> Entry {
     let message = commit
       .message()
       .split("\n")
       .nth(0)
-      .expect(" 信息不存在")
+      .unwrap_or_else(|| panic!("{}", t!("changelog.message_missing")))
       .trim();
 
-    let author = commit.author().as_ref().expect("author 不存在");
+    let author = commit
+      .author()
+      .as_ref()
+      .unwrap_or_else(|| panic!("{}", t!("changelog.author_missing")));
     let md_hash = commit.hash().trim();
     let short_md_hash = &md_hash[0..7];
 
+    let commit_url = format!(
+      "{github_url}/commit/{short_md_hash}",
+      github_url = self.github_html_url,
+      short_md_hash = short_md_hash
+    );
+
     let re = Regex::new(r"\(#[0-9]*\)").unwrap();
 
-    if re.is_match(message) {
+    let (pull_request, author) = if re.is_match(message) {
       let pr_id = re
         .captures(message)
         .unwrap()
@@ -204,48 +557,148 @@ impl Changelogs {
         .replace("(", "")
         .replace(")", "");
       let github_user_id = self.get_pr_user_name(&pr_id, author);
+      let number = pr_id.trim_start_matches('#').to_owned();
       let pr_url = format!(
-        "{github_url}/pull/{pr_id}",
+        "{github_url}/pull/{number}",
         github_url = self.github_html_url,
-        pr_id = pr_id
+        number = number
       );
+      let pull_request = match self.pull_requests.get(&number) {
+        Some(pr) => PullRequest {
+          title: pr.title.clone(),
+          labels: pr.labels.iter().map(|label| label.name.clone()).collect(),
+          merged_by: pr.merged_by.as_ref().map(|user| user.login.clone()),
+          number,
+          url: pr_url,
+        },
+        None => PullRequest {
+          number,
+          url: pr_url,
+          ..PullRequest::default()
+        },
+      };
+      (Some(pull_request), github_user_id)
+    } else {
+      (None, author.to_owned())
+    };
+    let issues = self.get_issue_links(commit.message(), pull_request.as_ref());
 
-      let md_message = format!(
-        "{message}. [{pr_id}]({pr_url}) [@{github_user_id}](https://github.com/{github_user_id})",
-        pr_id = pr_id,
-        message = message,
-        pr_url = pr_url,
-        github_user_id = github_user_id,
-      );
+    Entry {
+      message: message.to_owned(),
+      hash: md_hash.to_owned(),
+      short_hash: short_md_hash.to_owned(),
+      url: commit_url,
+      pull_request,
+      author,
+      reverts: parse_revert(commit.message()).map(|revert| revert.hash.to_owned()),
+      issues,
+    }
+  }
 
-      return md_message;
+  /* commit 和 pr 的描述中关闭的 issue 和提到的 jira，subject 中的 pr 不会重复出现 */
+  fn get_issue_links(
+    &mut self,
+    message: &str,
+    pull_request: Option<&PullRequest>,
+  ) -> Vec<IssueLink> {
+    let mut issue_links = vec![];
+    let mut issue_refs = parse_issue_refs(message, self.jira.as_ref());
+    if let Some(pr_refs) = pull_request.and_then(|pr| self.pull_request_refs.get(&pr.number)) {
+      merge_issue_refs(&mut issue_refs, pr_refs.iter().cloned());
+    }
+    for issue_ref in issue_refs {
+      match &issue_ref {
+        IssueRef::GitHub { repo: None, number }
+          if pull_request.is_some_and(|pull_request| &pull_request.number == number) => {}
+        IssueRef::GitHub { repo, number } => {
+          issue_links.push(self.get_github_issue(&issue_ref, repo.as_deref(), number))
+        }
+        IssueRef::Jira(key) => {
+          if let Some(jira) = &self.jira {
+            issue_links.push(IssueLink {
+              kind: IssueKind::Jira,
+              label: key.clone(),
+              url: jira.url(key),
+            });
+          }
+        }
+      }
     }
+    issue_links
+  }
 
-    let commit_or_pr_url = format!(
-      "{github_url}/commit/{short_md_hash}",
-      github_url = self.github_html_url,
-      short_md_hash = short_md_hash
-    );
+  /**
+   * 通过 issues 的接口区分 issue 和 pr，pr 也可以通过 issues 的接口获取
+   * 没有 GITHUB_TOKEN 或者请求失败时当作 issue
+   */
+  fn get_github_issue(
+    &mut self,
+    issue_ref: &IssueRef,
+    repo: Option<&str>,
+    number: &str,
+  ) -> IssueLink {
+    let repo_name = repo.unwrap_or(&self.repo_name).to_owned();
+    let key = format!("{}#{}", repo_name, number);
+    if let Some(issue_link) = self.issue_links.get(&key) {
+      return IssueLink {
+        label: issue_ref.label(),
+        ..issue_link.clone()
+      };
+    }
 
-    let md_message = format!(
-      "{message}. [{short_md_hash}]({commit_or_pr_url})",
-      short_md_hash = short_md_hash,
-      message = message,
-      commit_or_pr_url = commit_or_pr_url,
-    );
+    let mut issue_link = IssueLink {
+      kind: IssueKind::Issue,
+      label: issue_ref.label(),
+      url: format!("{}/issues/{}", self.repo_html_url(&repo_name), number),
+    };
+    if let Ok(token) = env::var("GITHUB_TOKEN") {
+      let issue_url = format!(
+        "{api_url}/repos/{repo_name}/issues/{number}",
+        api_url = self.api_url,
+        repo_name = repo_name,
+        number = number
+      );
+      let issue = self
+        .client
+        .get(&issue_url)
+        .header("Authorization", "token ".to_owned() + &token)
+        .header("Accept", "application/vnd.github.v3+json")
+        .send()
+        .and_then(|mut response| response.json::<GithubIssue>());
+      match issue {
+        Ok(issue) => {
+          issue_link.url = issue.html_url;
+          if issue.pull_request.is_some() {
+            issue_link.kind = IssueKind::PullRequest;
+          }
+        }
+        Err(err) => debug!("{}", t!("changelog.issue_lookup_failed", key, err)),
+      }
+    }
+    self.issue_links.insert(key, issue_link.clone());
+    issue_link
+  }
 
-    md_message
+  /* 同一个托管平台上其他仓库的网页地址 */
+  fn repo_html_url(&self, repo_name: &str) -> String {
+    match self.github_html_url.strip_suffix(&self.repo_name) {
+      Some(base_url) => format!("{}{}", base_url, repo_name),
+      None => format!("https://github.com/{}", repo_name),
+    }
   }
 
   /**
    * 通过pr的name 获取真实姓名，不让name 和 id 对不上
    */
   pub fn get_pr_user_name(&mut self, pr_number: &str, author: &str) -> String {
-    if self.author_github_map.get(author).is_none() {
+    let pr_number = pr_number.replace("#", "").trim().to_owned();
+    // 开启了 pr_details 时每个 pr 都需要请求一次，否则每个作者请求一次就够了
+    let need_details = self.pr_details && !self.pull_requests.contains_key(&pr_number);
+    if !self.author_github_map.contains_key(author) || need_details {
       let pr_url = format!(
-        "{github_url}{repo_name}/pulls/{pr_number}",
-        github_url = " https://api.github.com/repos/",
-        pr_number = pr_number.replace("#", "").trim(),
+        "{api_url}/repos/{repo_name}/pulls/{pr_number}",
+        api_url = self.api_url,
+        pr_number = pr_number,
         repo_name = self.repo_name,
       );
 
@@ -254,7 +707,9 @@ impl Changelogs {
         .get(&pr_url)
         .header(
           "Authorization",
-          "token ".to_owned() + &env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN 未找到"),
+          "token ".to_owned()
+            + &env::var("GITHUB_TOKEN")
+              .unwrap_or_else(|_| panic!("{}", t!("changelog.token_missing"))),
         )
         .header("Accept", "application/vnd.github.v3+json")
         .send()
@@ -263,9 +718,15 @@ impl Changelogs {
 
       if body.is_ok() {
         let pr = body.unwrap();
+        let refs = parse_issue_refs(pr.body.as_deref().unwrap_or_default(), self.jira.as_ref());
+        self.pull_request_refs.insert(pr_number.clone(), refs);
         self
           .author_github_map
-          .insert(author.to_owned(), pr.user.login.to_owned());
+          .entry(author.to_owned())
+          .or_insert_with(|| pr.user.login.to_owned());
+        if self.pr_details {
+          self.pull_requests.insert(pr_number, pr);
+        }
       }
     }
 
@@ -276,52 +737,545 @@ impl Changelogs {
     }
     author.to_string()
   }
+  /**
+   * 设置 commit 归属到包的方式，默认根据 scope
+   */
+  pub fn with_commit_match(mut self, commit_match: CommitMatch) -> Changelogs {
+    self.commit_match = commit_match;
+    self
+  }
+
+  /**
+   * 设置遍历 commit 的方式，比如只看 first parent，或者跳过 merge commit
+   */
+  pub fn with_walk_options(mut self, walk_options: WalkOptions) -> Changelogs {
+    self.walk_options = walk_options;
+    self
+  }
+
+  /**
+   * 设置预发布版本的处理方式，默认每个预发布版本都单独生成
+   */
+  pub fn with_release_options(mut self, release_options: ReleaseOptions) -> Changelogs {
+    self.release_options = release_options;
+    self
+  }
+
+  /**
+   * 设置是否在版本的标题下面输出 tag 的注释，只有 annotated tag 有注释
+   */
+  pub fn with_tag_summary(mut self, tag_summary: bool) -> Changelogs {
+    self.tag_summary = tag_summary;
+    self
+  }
+
+  /**
+   * 设置 html 中是否显示贡献者的 github 头像，默认不显示
+   */
+  pub fn with_avatars(mut self, avatars: bool) -> Changelogs {
+    self.avatars = avatars;
+    self
+  }
+
+  /**
+   * 设置 jira 这样的 issue 的 key 和链接，commit 信息中的 key 会输出为链接
+   */
+  pub fn with_jira(mut self, jira: JiraKeys) -> Changelogs {
+    self.jira = Some(jira);
+    self
+  }
+
+  /**
+   * 设置是否获取 pr 的标题、label 和合并的人，标题会代替 commit 信息输出，label 会用来分组
+   */
+  pub fn with_pr_details(mut self, pr_details: bool) -> Changelogs {
+    self.pr_details = pr_details;
+    self
+  }
+
+  /**
+   * 设置输出的格式，默认为 markdown
+   */
+  pub fn with_format(mut self, format: OutputFormat) -> Changelogs {
+    self.format = format;
+    self
+  }
+
+  /**
+   * 设置自定义的 handlebars 模板，可以控制版本、包和每一条 commit 的输出
+   */
+  pub fn with_template(mut self, template: Template) -> Changelogs {
+    self.template = Some(template);
+    self
+  }
+
+  /**
+   * 设置需要生成 changelog 的包，包名是 <package_prefix><package>
+   */
+  pub fn with_packages(mut self, package_prefix: String, package_list: Vec<String>) -> Changelogs {
+    self.package_prefix = package_prefix;
+    self.package_list = package_list;
+    self
+  }
+
+  /**
+   * 设置包名的前缀，需要生成 changelog 的包为 workspace 中带有这个前缀的包
+   */
+  pub fn with_package_prefix(mut self, package_prefix: String) -> Changelogs {
+    self.package_list = self
+      .package_dirs
+      .iter()
+      .filter_map(|(name, _)| name.strip_prefix(&package_prefix))
+      .map(String::from)
+      .collect();
+    self.package_prefix = package_prefix;
+    self
+  }
+
+  /**
+   * 设置需要生成 changelog 的包，不带前缀
+   */
+  pub fn with_package_list(mut self, package_list: Vec<String>) -> Changelogs {
+    self.package_list = package_list;
+    self
+  }
+
+  /**
+   * 设置包所在的目录，默认从 workspace 的配置中找到每个包的目录
+   */
+  pub fn with_packages_dir(mut self, packages_dir: String) -> Changelogs {
+    self.packages_dir = Some(packages_dir);
+    self
+  }
+
+  /**
+   * 设置过滤 commit 的规则，默认会过滤掉 [skip changelog]、chore/docs/test、机器人和相互抵消的 revert
+   */
+  pub fn with_filters(mut self, filters: CommitFilters) -> Changelogs {
+    self.filters = filters;
+    self
+  }
+
+  /**
+   * 设置 keep-a-changelog 中 commit 的类型对应的分组
+   */
+  pub fn with_commit_types(mut self, commit_types: CommitTypes) -> Changelogs {
+    self.commit_types = commit_types;
+    self
+  }
+
   /**
    * 初始化，需要添加项目的地址
    */
   pub fn new(repo: String) -> Changelogs {
+    Changelogs::new_with_forge(repo, Forge::default())
+  }
+
+  /**
+   * 初始化，托管平台的配置中没有的信息从 origin 和 api 中获取
+   */
+  pub fn new_with_forge(repo: String, forge: Forge) -> Changelogs {
     let author_github_map = HashMap::new();
     let client = Client::new();
     let repo = Repository::open(repo).unwrap();
+    let api_url = forge
+      .api_url
+      .map(|api_url| api_url.trim_end_matches('/').to_owned())
+      .unwrap_or_else(|| GITHUB_API_URL.to_owned());
 
     //  仓库的 http 地址，用于生成 commit 的链接
-    let repo_name = repo
-      .find_remote("origin")
-      .unwrap()
-      .url()
-      .unwrap()
-      // git@github.com:ant-design/pro-components.git
-      // -> ant-design/pro-components.git
-      .split(":")
-      .nth(1)
-      .unwrap()
-      .split(".")
-      // ant-design/pro-components.git -> ant-design/pro-components
-      .nth(0)
-      .unwrap()
-      .to_owned();
+    let repo_name = match forge.repo_name {
+      Some(repo_name) => repo_name,
+      None => repo
+        .find_remote("origin")
+        .unwrap()
+        .url()
+        .unwrap()
+        // git@github.com:ant-design/pro-components.git
+        // -> ant-design/pro-components.git
+        .split(":")
+        .nth(1)
+        .unwrap()
+        .split(".")
+        // ant-design/pro-components.git -> ant-design/pro-components
+        .nth(0)
+        .unwrap()
+        .to_owned(),
+    };
 
-    let url = format!(
-      "https://api.github.com/repos/{repo_name}",
-      repo_name = repo_name
-    );
+    let html_url = match forge.html_url {
+      Some(html_url) => html_url.trim_end_matches('/').to_owned(),
+      None => {
+        let url = format!(
+          "{api_url}/repos/{repo_name}",
+          api_url = api_url,
+          repo_name = repo_name
+        );
 
-    let body: GithubRepo = client
-      .get(&url)
-      .header("Accept", "application/vnd.github.v3+json")
-      .send()
-      .unwrap()
-      .json()
-      .expect("json 转化失败，请检查是网络错误，或者 GITHUB_TOKEN 是否失效！");
+        let body: GithubRepo = client
+          .get(&url)
+          .header("Accept", "application/vnd.github.v3+json")
+          .send()
+          .unwrap()
+          .json()
+          .unwrap_or_else(|_| panic!("{}", t!("changelog.repo_failed")));
 
-    let html_url = body.html_url;
+        body.html_url
+      }
+    };
+    let package_dirs = workspace_package_dirs(&repo);
+    let package_names: Vec<String> = package_dirs.iter().map(|(name, _)| name.clone()).collect();
+    let package_prefix = common_package_prefix(&package_names);
 
     Changelogs {
       repo,
       client: client,
       author_github_map: author_github_map,
       github_html_url: html_url,
+      api_url,
       repo_name: repo_name,
+      commit_match: CommitMatch::Scope,
+      walk_options: WalkOptions::default(),
+      release_options: ReleaseOptions::default(),
+      tag_summary: false,
+      packages_dir: None,
+      package_list: package_names
+        .iter()
+        .map(|name| name[package_prefix.len()..].to_string())
+        .collect(),
+      package_dirs,
+      package_prefix,
+      commit_types: CommitTypes::default(),
+      format: OutputFormat::Markdown,
+      template: None,
+      filters: CommitFilters::default(),
+      first_commits: None,
+      avatars: false,
+      jira: None,
+      issue_links: HashMap::new(),
+      pr_details: false,
+      pull_requests: HashMap::new(),
+      pull_request_refs: HashMap::new(),
     }
   }
 }
+
+/**
+ * workspace 中每个包的包名和相对仓库根目录的路径，bare 仓库没有工作目录
+ * 没有 workspace 的包时使用根目录的 package.json，路径为空
+ */
+fn workspace_package_dirs(repo: &Repository) -> Vec<(String, String)> {
+  let workdir = match repo.workdir() {
+    Some(workdir) => workdir,
+    None => return vec![],
+  };
+  let package_dirs: Vec<(String, String)> = find_packages(&workdir.display().to_string())
+    .iter()
+    .filter_map(|package| {
+      let dir = Path::new(package.path()).strip_prefix(workdir).ok()?;
+      let dir = dir.to_string_lossy().replace('\\', "/");
+      Some((package.name().to_owned(), format!("{}/", dir)))
+    })
+    .collect();
+  if !package_dirs.is_empty() {
+    return package_dirs;
+  }
+  fs::read_to_string(workdir.join("package.json"))
+    .ok()
+    .and_then(|data| serde_json::from_str::<RootPackage>(&data).ok())
+    .and_then(|package| package.name)
+    .map(|name| vec![(name, String::new())])
+    .unwrap_or_default()
+}
+
+/* 多个包的包名共同的前缀，截止到最后一个 / 或者 -，比如 @ant-design/pro- */
+fn common_package_prefix(names: &[String]) -> String {
+  if names.len() < 2 {
+    return String::new();
+  }
+  let mut prefix = names[0].as_str();
+  for name in &names[1..] {
+    let len = prefix
+      .char_indices()
+      .zip(name.chars())
+      .find(|((_, a), b)| a != b)
+      .map(|((index, _), _)| index)
+      .unwrap_or_else(|| prefix.len().min(name.len()));
+    prefix = &prefix[..len];
+  }
+  match prefix.rfind(['/', '-']) {
+    Some(index) => prefix[..=index].to_string(),
+    None => String::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use std::path::{Path, PathBuf};
+
+  use super::filter::CommitFilters;
+  use super::git::tests::TestRepo;
+  use super::render::PackageNotes;
+  use super::{common_package_prefix, Changelogs, CommitMatch, Forge, GithubPull};
+
+  fn write_package(dir: &Path, content: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("package.json"), content).unwrap();
+  }
+
+  fn changelogs(repo: &TestRepo) -> Changelogs {
+    Changelogs::new_with_forge(
+      repo.path(),
+      Forge {
+        html_url: Some("https://github.com/ant-design/pro-components".to_string()),
+        api_url: None,
+        repo_name: Some("ant-design/pro-components".to_string()),
+      },
+    )
+  }
+
+  /* 每个包的条目，按照 commit 信息排序 */
+  fn messages(package_notes_list: &[PackageNotes]) -> Vec<(&str, Vec<&str>)> {
+    package_notes_list
+      .iter()
+      .map(|package_notes| {
+        let mut messages: Vec<&str> = package_notes.releases[0]
+          .entries
+          .iter()
+          .map(|entry| entry.message.as_str())
+          .collect();
+        messages.sort_unstable();
+        (package_notes.package.as_str(), messages)
+      })
+      .collect()
+  }
+
+  fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+  }
+
+  #[test]
+  fn it_finds_workspace_packages() {
+    assert_eq!(
+      common_package_prefix(&names(&[
+        "@ant-design/pro-components",
+        "@ant-design/pro-card",
+        "@ant-design/pro-table"
+      ])),
+      "@ant-design/pro-"
+    );
+    assert_eq!(
+      common_package_prefix(&names(&["@scope/a", "@scope/b"])),
+      "@scope/"
+    );
+    assert_eq!(common_package_prefix(&names(&["react", "redux"])), "");
+    assert_eq!(common_package_prefix(&names(&["@scope/a"])), "");
+
+    let repo = TestRepo::new();
+    let path = PathBuf::from(repo.path());
+    fs::write(path.join("package.json"), r#"{ "name": "doctor" }"#).unwrap();
+    let single = changelogs(&repo);
+    assert_eq!(single.package_prefix, "");
+    assert_eq!(single.package_list, names(&["doctor"]));
+    assert_eq!(single.package_path("doctor"), "");
+
+    write_package(
+      &path.join("packages/table"),
+      r#"{ "name": "@ant-design/pro-table", "version": "1.0.0" }"#,
+    );
+    write_package(
+      &path.join("packages/form"),
+      r#"{ "name": "@ant-design/pro-form", "version": "1.0.0" }"#,
+    );
+    let workspace = changelogs(&repo);
+    assert_eq!(workspace.package_prefix, "@ant-design/pro-");
+    assert_eq!(workspace.package_list, names(&["form", "table"]));
+    assert_eq!(workspace.package_path("table"), "packages/table/");
+
+    let workspace = workspace.with_package_prefix("@ant-design/".to_string());
+    assert_eq!(workspace.package_list, names(&["pro-form", "pro-table"]));
+  }
+
+  #[test]
+  fn it_matches_commits_by_workspace_paths() {
+    let mut repo = TestRepo::new();
+    let path = PathBuf::from(repo.path());
+    fs::write(
+      path.join("package.json"),
+      r#"{ "private": true, "workspaces": ["packages/*/*", "apps/*"] }"#,
+    )
+    .unwrap();
+    write_package(
+      &path.join("packages/pro/table"),
+      r#"{ "name": "@ant-design/pro-table", "version": "1.0.0" }"#,
+    );
+    write_package(
+      &path.join("packages/pro/form"),
+      r#"{ "name": "@ant-design/pro-form", "version": "1.0.0" }"#,
+    );
+    write_package(
+      &path.join("apps/site"),
+      r#"{ "name": "@ant-design/pro-site", "version": "1.0.0" }"#,
+    );
+
+    let root = repo.commit(&[], "root");
+    let sort = repo.commit_change(
+      root,
+      "fix: sort the columns",
+      "packages/pro/table/src/Table.tsx",
+      "a\n",
+    );
+    let field = repo.commit_change(
+      sort,
+      "feat(form): add a field",
+      "packages/pro/form/index.ts",
+      "a\n",
+    );
+    let typo = repo.commit_change(field, "fix: typo on the site", "apps/site/index.md", "a\n");
+    let scroll = repo.commit_change(
+      typo,
+      "fix(table): keep the scroll",
+      "packages/pro/form/src/Form.tsx",
+      "a\n",
+    );
+    repo.tag(root, "v1");
+    repo.tag(scroll, "v2");
+
+    let packages = vec!["table".to_string(), "form".to_string(), "site".to_string()];
+    let mut path_changelogs = changelogs(&repo)
+      .with_packages("@ant-design/pro-".to_string(), packages.clone())
+      .with_commit_match(CommitMatch::Path);
+    assert_eq!(
+      messages(
+        &path_changelogs
+          .get_ref_range_change_log_list("v1", "v2")
+          .unwrap()
+      ),
+      vec![
+        ("table", vec!["fix: sort the columns"]),
+        (
+          "form",
+          vec!["feat(form): add a field", "fix(table): keep the scroll"]
+        ),
+        ("site", vec!["fix: typo on the site"]),
+      ]
+    );
+
+    let mut scope_or_path_changelogs = changelogs(&repo)
+      .with_packages("@ant-design/pro-".to_string(), packages)
+      .with_commit_match(CommitMatch::ScopeOrPath);
+    assert_eq!(
+      messages(
+        &scope_or_path_changelogs
+          .get_ref_range_change_log_list("v1", "v2")
+          .unwrap()
+      )[0],
+      (
+        "table",
+        vec!["fix(table): keep the scroll", "fix: sort the columns"]
+      )
+    );
+  }
+
+  #[test]
+  fn it_only_lists_reverts_of_earlier_releases() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let old = repo.commit(&[root], "feat(table): add a footer");
+    let toolbar = repo.commit(&[old], "feat(table): add a toolbar");
+    let revert_toolbar = repo.commit(
+      &[toolbar],
+      &format!(
+        "Revert \"feat(table): add a toolbar\"\n\nThis reverts commit {}.",
+        toolbar
+      ),
+    );
+    let revert_old = repo.commit(
+      &[revert_toolbar],
+      &format!(
+        "Revert \"feat(table): add a footer\"\n\nThis reverts commit {}.",
+        &old.to_string()[..7]
+      ),
+    );
+    repo.tag(old, "v1");
+    repo.tag(revert_old, "v2");
+
+    // exclude.reverts 为 false 时，版本内相互抵消的 commit 都会保留
+    let mut changelogs = changelogs(&repo)
+      .with_packages("@ant-design/pro-".to_string(), vec!["table".to_string()])
+      .with_filters(CommitFilters::empty());
+    let package_notes_list = changelogs
+      .get_ref_range_change_log_list("v1", "v2")
+      .unwrap();
+    let mut reverts: Vec<(&str, Option<&str>)> = package_notes_list[0].releases[0]
+      .entries
+      .iter()
+      .map(|entry| (entry.message.as_str(), entry.reverts.as_deref()))
+      .collect();
+    reverts.sort_unstable();
+    let old = old.to_string();
+    assert_eq!(
+      reverts,
+      vec![
+        ("Revert \"feat(table): add a footer\"", Some(&old[..7])),
+        ("Revert \"feat(table): add a toolbar\"", None),
+        ("feat(table): add a toolbar", None),
+      ]
+    );
+  }
+
+  #[test]
+  fn it_marks_first_listed_contributions() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let first = repo.commit_as(&[root], "feat(table): add a footer", "alice");
+    let footer = repo.commit_as(&[first], "fix(table): fix the footer", "alice");
+    let toolbar = repo.commit_as(&[footer], "feat(table): add a toolbar", "bob");
+    let chore = repo.commit_as(&[toolbar], "chore(table): rename", "carol");
+    let scroll = repo.commit_as(&[chore], "fix(table): keep the scroll", "carol");
+    let field = repo.commit_as(&[scroll], "feat(form): add a field", "dave");
+    repo.tag(first, "v1");
+    repo.tag(field, "v2");
+
+    let mut changelogs = changelogs(&repo).with_packages(
+      "@ant-design/pro-".to_string(),
+      vec!["table".to_string(), "form".to_string()],
+    );
+    let package_notes_list = changelogs
+      .get_ref_range_change_log_list("v1", "v2")
+      .unwrap();
+    let contributors: Vec<Vec<(&str, bool)>> = package_notes_list
+      .iter()
+      .map(|package_notes| {
+        let mut contributors: Vec<(&str, bool)> = package_notes.releases[0]
+          .contributors
+          .iter()
+          .map(|contributor| (contributor.name.as_str(), contributor.first_time))
+          .collect();
+        contributors.sort_unstable();
+        contributors
+      })
+      .collect();
+    // carol 的第一个 commit 被过滤掉了，dave 的第一个 commit 属于 form
+    assert_eq!(
+      contributors,
+      vec![
+        vec![("alice", false), ("bob", true), ("carol", false)],
+        vec![("dave", true)],
+      ]
+    );
+  }
+
+  #[test]
+  fn it_reads_pull_request_bodies() {
+    let pr: GithubPull = serde_json::from_str(
+      r#"{ "user": { "login": "chenshuai2144" }, "body": "Fixes #12\r\nCloses PROJ-7" }"#,
+    )
+    .unwrap();
+    assert_eq!(pr.body.as_deref(), Some("Fixes #12\r\nCloses PROJ-7"));
+    let pr: GithubPull =
+      serde_json::from_str(r#"{ "user": { "login": "chenshuai2144" }, "body": null }"#).unwrap();
+    assert!(pr.body.is_none());
+  }
+}
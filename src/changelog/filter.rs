@@ -0,0 +1,381 @@
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use super::git::Commit;
+use super::render::conventional_kind;
+
+/// Decides whether a commit is left out of the changelog.
+pub trait CommitFilter {
+  /// A short name shown in the debug logs.
+  fn name(&self) -> &str;
+
+  /// Called with the commits of a release before [`CommitFilter::exclude`],
+  /// for filters that look at other commits of the release.
+  fn prepare(&mut self, _commits: &[Commit]) {}
+
+  /// Whether to drop the commit.
+  fn exclude(&self, commit: &Commit) -> bool;
+}
+
+/// Drops commits with a marker such as `[skip changelog]` anywhere in the
+/// message.
+pub struct SkipMarker {
+  markers: Vec<String>,
+}
+
+impl SkipMarker {
+  pub const DEFAULT: [&'static str; 2] = ["[skip changelog]", "[changelog skip]"];
+
+  #[must_use]
+  pub fn new(markers: Vec<String>) -> SkipMarker {
+    SkipMarker { markers }
+  }
+}
+
+impl Default for SkipMarker {
+  fn default() -> SkipMarker {
+    SkipMarker::new(SkipMarker::DEFAULT.map(String::from).to_vec())
+  }
+}
+
+impl CommitFilter for SkipMarker {
+  fn name(&self) -> &str {
+    "skip-marker"
+  }
+
+  fn exclude(&self, commit: &Commit) -> bool {
+    let message = commit.message().to_lowercase();
+    self
+      .markers
+      .iter()
+      .any(|marker| message.contains(&marker.to_lowercase()))
+  }
+}
+
+/// Drops conventional commits of some types, such as `chore: ...`.
+pub struct ExcludedTypes {
+  types: Vec<String>,
+}
+
+impl ExcludedTypes {
+  pub const DEFAULT: [&'static str; 3] = ["chore", "docs", "test"];
+
+  #[must_use]
+  pub fn new(types: Vec<String>) -> ExcludedTypes {
+    ExcludedTypes { types }
+  }
+}
+
+impl Default for ExcludedTypes {
+  fn default() -> ExcludedTypes {
+    ExcludedTypes::new(ExcludedTypes::DEFAULT.map(String::from).to_vec())
+  }
+}
+
+impl CommitFilter for ExcludedTypes {
+  fn name(&self) -> &str {
+    "commit-type"
+  }
+
+  fn exclude(&self, commit: &Commit) -> bool {
+    let subject = commit.message().lines().next().unwrap_or_default();
+    conventional_kind(subject)
+      .is_some_and(|kind| self.types.iter().any(|excluded| excluded == kind))
+  }
+}
+
+/// Drops commits by bots, any author ending with `[bot]` and authors
+/// containing one of the names.
+pub struct BotAuthors {
+  names: Vec<String>,
+}
+
+impl BotAuthors {
+  pub const DEFAULT: [&'static str; 2] = ["dependabot", "renovate"];
+
+  #[must_use]
+  pub fn new(names: Vec<String>) -> BotAuthors {
+    BotAuthors { names }
+  }
+}
+
+impl Default for BotAuthors {
+  fn default() -> BotAuthors {
+    BotAuthors::new(BotAuthors::DEFAULT.map(String::from).to_vec())
+  }
+}
+
+impl CommitFilter for BotAuthors {
+  fn name(&self) -> &str {
+    "bot-author"
+  }
+
+  fn exclude(&self, commit: &Commit) -> bool {
+    let author = match commit.author() {
+      Some(author) => author.to_lowercase(),
+      None => return false,
+    };
+    author.ends_with("[bot]")
+      || self
+        .names
+        .iter()
+        .any(|name| author.contains(&name.to_lowercase()))
+  }
+}
+
+/// A commit reverting another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Revert<'a> {
+  /// The reverted commit, from the `This reverts commit <hash>.` line git
+  /// adds to the body.
+  pub hash: &'a str,
+  /// The subject of the reverted commit, from a `Revert "<subject>"` or
+  /// `revert: <subject>` subject.
+  pub subject: Option<&'a str>,
+}
+
+/// Parse a revert commit message, `None` unless the body names the reverted
+/// commit.
+#[must_use]
+pub fn parse_revert(message: &str) -> Option<Revert<'_>> {
+  static REVERTED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"This reverts commit ([0-9a-fA-F]{7,40})").unwrap());
+  let hash = REVERTED.captures(message)?.get(1)?.as_str();
+  let first_line = message.lines().next().unwrap_or_default().trim();
+  let subject = match first_line.strip_prefix("Revert \"") {
+    Some(quoted) => quoted.strip_suffix('"'),
+    None if conventional_kind(first_line) == Some("revert") => first_line
+      .split_once(':')
+      .map(|(_, subject)| subject.trim()),
+    None => None,
+  };
+  Some(Revert { hash, subject })
+}
+
+/// Whether two hashes, full or abbreviated, name the same commit.
+pub fn same_hash(left: &str, right: &str) -> bool {
+  left.starts_with(right) || right.starts_with(left)
+}
+
+/// Drops a commit and its revert when both are in the same release.
+///
+/// Commits are expected newest first, so a revert of a revert cancels the
+/// first revert and the original commit is kept.
+#[derive(Default)]
+pub struct RevertPairs {
+  paired: HashSet<String>,
+}
+
+impl CommitFilter for RevertPairs {
+  fn name(&self) -> &str {
+    "revert-pair"
+  }
+
+  fn prepare(&mut self, commits: &[Commit]) {
+    self.paired.clear();
+    for commit in commits {
+      if self.paired.contains(commit.hash()) {
+        continue;
+      }
+      let revert = match parse_revert(commit.message()) {
+        Some(revert) => revert,
+        None => continue,
+      };
+      if let Some(original) = commits
+        .iter()
+        .find(|original| same_hash(original.hash(), revert.hash))
+      {
+        self.paired.insert(original.hash().to_string());
+        self.paired.insert(commit.hash().to_string());
+      }
+    }
+  }
+
+  fn exclude(&self, commit: &Commit) -> bool {
+    self.paired.contains(commit.hash())
+  }
+}
+
+/// Drops commits by hash, abbreviated hashes are allowed.
+pub struct ExcludedHashes {
+  hashes: Vec<String>,
+}
+
+impl ExcludedHashes {
+  #[must_use]
+  pub fn new(hashes: Vec<String>) -> ExcludedHashes {
+    ExcludedHashes {
+      hashes: hashes.iter().map(|hash| hash.to_lowercase()).collect(),
+    }
+  }
+}
+
+impl CommitFilter for ExcludedHashes {
+  fn name(&self) -> &str {
+    "excluded-hash"
+  }
+
+  fn exclude(&self, commit: &Commit) -> bool {
+    self
+      .hashes
+      .iter()
+      .any(|hash| commit.hash().starts_with(hash.as_str()))
+  }
+}
+
+/// The filters a release goes through before its commits become entries.
+pub struct CommitFilters {
+  filters: Vec<Box<dyn CommitFilter>>,
+}
+
+impl CommitFilters {
+  /// A list without any filter, every commit is kept.
+  #[must_use]
+  pub fn empty() -> CommitFilters {
+    CommitFilters { filters: vec![] }
+  }
+
+  /// Add a filter to the list.
+  #[must_use]
+  pub fn with_filter(mut self, filter: impl CommitFilter + 'static) -> CommitFilters {
+    self.filters.push(Box::new(filter));
+    self
+  }
+
+  /// Drop the commits any filter excludes, with the name of the filter.
+  pub fn split(&mut self, commits: Vec<Commit>) -> (Vec<Commit>, Vec<(Commit, String)>) {
+    for filter in &mut self.filters {
+      filter.prepare(&commits);
+    }
+    let mut kept = vec![];
+    let mut excluded = vec![];
+    for commit in commits {
+      match self.filters.iter().find(|filter| filter.exclude(&commit)) {
+        Some(filter) => {
+          let name = filter.name().to_string();
+          excluded.push((commit, name));
+        }
+        None => kept.push(commit),
+      }
+    }
+    (kept, excluded)
+  }
+}
+
+impl Default for CommitFilters {
+  fn default() -> CommitFilters {
+    CommitFilters::empty()
+      .with_filter(SkipMarker::default())
+      .with_filter(ExcludedTypes::default())
+      .with_filter(BotAuthors::default())
+      .with_filter(RevertPairs::default())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse_revert, CommitFilters, ExcludedHashes, Revert};
+  use crate::changelog::git::Commit;
+
+  fn commit(message: &str, hash: &str, author: &str) -> Commit {
+    Commit::new(message, hash, author)
+  }
+
+  fn messages(commits: &[Commit]) -> Vec<&str> {
+    commits.iter().map(|commit| commit.message()).collect()
+  }
+
+  #[test]
+  fn it_filters_noise() {
+    let feature = "1111111111111111111111111111111111111111";
+    let commits = vec![
+      commit("feat(table): add a column", "a000000", "chenshuai2144"),
+      commit("fix(form): fix the label [skip changelog]", "b000000", "chenshuai2144"),
+      commit("chore(table): bump the version", "c000000", "chenshuai2144"),
+      commit("docs: fix a typo", "d000000", "chenshuai2144"),
+      commit("fix(deps): bump antd", "e000000", "dependabot[bot]"),
+      commit("fix(deps): update antd", "f000000", "Renovate Bot"),
+      commit("feat(table): add a toolbar", feature, "chenshuai2144"),
+      commit(
+        &format!("Revert \"feat(table): add a toolbar\"\n\nThis reverts commit {}.", feature),
+        "0100000",
+        "chenshuai2144",
+      ),
+      commit(
+        "Revert \"feat(table): an older feature\"\n\nThis reverts commit 9999999999999999999999999999999999999999.",
+        "0200000",
+        "chenshuai2144",
+      ),
+      commit("fix(table): fix the filter", "0300000", "chenshuai2144"),
+    ];
+
+    let (kept, excluded) = CommitFilters::default()
+      .with_filter(ExcludedHashes::new(vec!["03000".to_string()]))
+      .split(commits);
+    assert_eq!(
+      messages(&kept),
+      vec![
+        "feat(table): add a column",
+        "Revert \"feat(table): an older feature\"\n\nThis reverts commit 9999999999999999999999999999999999999999.",
+      ]
+    );
+    let names: Vec<&str> = excluded.iter().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(
+      names,
+      vec![
+        "skip-marker",
+        "commit-type",
+        "commit-type",
+        "bot-author",
+        "bot-author",
+        "revert-pair",
+        "revert-pair",
+        "excluded-hash",
+      ]
+    );
+  }
+
+  #[test]
+  fn it_parses_reverts() {
+    assert_eq!(
+      parse_revert("Revert \"fix(form): a\"\n\nThis reverts commit 0123456789abcdef."),
+      Some(Revert {
+        hash: "0123456789abcdef",
+        subject: Some("fix(form): a"),
+      })
+    );
+    assert_eq!(
+      parse_revert("revert: fix(form): a\n\nThis reverts commit 0123456789abcdef."),
+      Some(Revert {
+        hash: "0123456789abcdef",
+        subject: Some("fix(form): a"),
+      })
+    );
+    assert_eq!(parse_revert("fix: revert the padding"), None);
+  }
+
+  #[test]
+  fn it_keeps_commits_reverted_twice() {
+    let feature = "1111111111111111111111111111111111111111";
+    let revert = "2222222222222222222222222222222222222222";
+    let commits = vec![
+      commit(
+        &format!(
+          "Revert \"Revert \"feat: a\"\"\n\nThis reverts commit {}.",
+          revert
+        ),
+        "3333333333333333333333333333333333333333",
+        "chenshuai2144",
+      ),
+      commit(
+        &format!("Revert \"feat: a\"\n\nThis reverts commit {}.", feature),
+        revert,
+        "chenshuai2144",
+      ),
+      commit("feat: a", feature, "chenshuai2144"),
+    ];
+    let (kept, _) = CommitFilters::default().split(commits);
+    assert_eq!(messages(&kept), vec!["feat: a"]);
+  }
+}
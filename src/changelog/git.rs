@@ -1,22 +1,26 @@
-﻿use chrono::prelude::*;
+﻿use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::*;
 use failure::ResultExt;
-use git2::{self, DiffStatsFormat, Repository};
+use git2::{self, DiffOptions, DiffStatsFormat, Patch, Repository, Sort};
 use semver::Version;
-use std::str;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::{self, FromStr};
 
+use super::history::package_full_commits;
+use crate::i18n::t;
 use crate::ErrorKind;
 
-#[derive(Clone, Debug)]
-pub struct TagAndVersion {
-  pub package: String,
-  pub version: String,
-}
+/// The tag name used for the commits since the latest release.
+pub const UNRELEASED: &str = "Unreleased";
 
 /// A git tag.
 #[derive(Clone, Debug)]
 pub struct Tag {
   pub name: String,
   pub date_time: String,
+  /// The message of an annotated tag.
+  pub summary: Option<String>,
 }
 
 impl Tag {
@@ -32,6 +36,43 @@ impl Tag {
 pub struct TagAndCommit {
   pub tag: Tag,
   pub commit_list: Vec<Commit>,
+  /// Set when [`WalkOptions::diff_stats`] is enabled.
+  pub stats: Option<DiffStats>,
+}
+
+/// The number of most touched paths kept in [`DiffStats`].
+const TOP_PATHS: usize = 5;
+
+/// Line changes of a single path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PathStats {
+  pub path: String,
+  pub insertions: usize,
+  pub deletions: usize,
+}
+
+/// Statistics of the changes in a range.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStats {
+  pub files_changed: usize,
+  pub insertions: usize,
+  pub deletions: usize,
+  /// The most touched paths, by changed lines.
+  pub top_paths: Vec<PathStats>,
+}
+
+impl DiffStats {
+  /// The localized number of files, insertions and deletions.
+  #[must_use]
+  pub fn summary(&self) -> String {
+    t!(
+      "changelog.stats",
+      self.files_changed,
+      self.insertions,
+      self.deletions
+    )
+  }
 }
 
 /// A commit range for a tagged release
@@ -39,7 +80,302 @@ pub struct TagAndCommit {
 pub struct CommitRange<'r> {
   latest_tag: Tag,
   start: git2::Commit<'r>,
-  end: git2::Commit<'r>,
+  /// The previous release, `None` when the range reaches the first commit.
+  end: Option<git2::Commit<'r>>,
+}
+
+impl<'r> CommitRange<'r> {
+  /// Access the tag of the range.
+  #[inline]
+  #[must_use]
+  pub fn tag(&self) -> &Tag {
+    &self.latest_tag
+  }
+
+  /// Access the newest commit of the range.
+  #[inline]
+  #[must_use]
+  pub fn start(&self) -> &git2::Commit<'r> {
+    &self.start
+  }
+
+  /// Access the previous release, excluded from the range.
+  #[inline]
+  #[must_use]
+  pub fn end(&self) -> Option<&git2::Commit<'r>> {
+    self.end.as_ref()
+  }
+}
+
+/// A tagged release of a package and the commits added since the release
+/// before it.
+#[derive(Clone, Debug)]
+pub struct Release<'r> {
+  pub version: Version,
+  pub range: CommitRange<'r>,
+}
+
+impl<'r> Release<'r> {
+  /// Access the release tag.
+  #[inline]
+  #[must_use]
+  pub fn tag(&self) -> &Tag {
+    &self.range.latest_tag
+  }
+}
+
+/// Options used when walking the commits of a range.
+#[derive(Clone, Debug, Default)]
+pub struct WalkOptions {
+  /// Collect the paths changed by each commit, needed to attribute
+  /// commits to packages by directory.
+  pub collect_paths: bool,
+  /// Only follow the first parent of merge commits.
+  pub first_parent: bool,
+  /// Leave merge commits out of the range.
+  pub no_merges: bool,
+  /// Compute the diff statistics of each range.
+  pub diff_stats: bool,
+  /// Restrict the diff statistics to a directory such as `packages/form/`.
+  pub stats_path: Option<String>,
+}
+
+/// How prerelease tags (alpha, beta, rc, ...) take part in the release
+/// history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrereleasePolicy {
+  /// Every prerelease tag is a release of its own.
+  Include,
+  /// Prerelease tags are ignored, their commits belong to the next stable
+  /// release.
+  Exclude,
+  /// Prereleases are listed until a stable release is tagged, then their
+  /// commits are rolled up into that release.
+  RollUp,
+}
+
+impl FromStr for PrereleasePolicy {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<PrereleasePolicy> {
+    match s {
+      "include" => Ok(PrereleasePolicy::Include),
+      "exclude" => Ok(PrereleasePolicy::Exclude),
+      "roll-up" => Ok(PrereleasePolicy::RollUp),
+      _ => Err(ErrorKind::Config(t!("changelog.prerelease_invalid", s)).into()),
+    }
+  }
+}
+
+/// Which tag counts as the latest release.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatestRelease {
+  /// The newest stable version.
+  Stable,
+  /// The newest version, prereleases included.
+  Any,
+}
+
+impl FromStr for LatestRelease {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<LatestRelease> {
+    match s {
+      "stable" => Ok(LatestRelease::Stable),
+      "any" => Ok(LatestRelease::Any),
+      _ => Err(ErrorKind::Config(t!("changelog.latest_invalid", s)).into()),
+    }
+  }
+}
+
+/// A tag naming template such as `{name}@{version}`, `v{version}` or
+/// `{name}-v{version}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagPattern {
+  template: String,
+}
+
+impl TagPattern {
+  /// The template used by lerna and changesets.
+  pub const DEFAULT: &'static str = "{name}@{version}";
+
+  /// Format the tag of a package version.
+  #[must_use]
+  pub fn format(&self, name: &str, version: &str) -> String {
+    self
+      .template
+      .replace("{name}", name)
+      .replace("{version}", version)
+  }
+
+  /// Get the version of a tag if it is a tag of the package.
+  #[must_use]
+  pub fn version(&self, tag: &str, name: &str) -> Option<Version> {
+    let template = self.template.replace("{name}", name);
+    let (prefix, suffix) = template.split_once("{version}")?;
+    let version = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    Version::parse(version).ok()
+  }
+
+  /// Split a tag of any package into its package name and version. The name
+  /// is empty when the template has no `{name}`.
+  #[must_use]
+  pub fn parse(&self, tag: &str) -> Option<(String, Version)> {
+    let (prefix, rest) = self.template.split_once("{version}")?;
+    if !prefix.contains("{name}") && !rest.contains("{name}") {
+      return self
+        .version(tag, "")
+        .map(|version| (String::new(), version));
+    }
+    // Try every position of the separator since names and versions may
+    // contain it too, e.g. `@scope/name@1.0.0`.
+    let name_first = prefix.contains("{name}");
+    let (head, separator, tail) = if name_first {
+      let (head, separator) = prefix.split_once("{name}")?;
+      (head, separator, rest)
+    } else {
+      let (separator, tail) = rest.split_once("{name}")?;
+      (prefix, separator, tail)
+    };
+    let body = tag.strip_prefix(head)?.strip_suffix(tail)?;
+    body.match_indices(separator).find_map(|(index, _)| {
+      let (left, right) = (&body[..index], &body[index + separator.len()..]);
+      let (name, version) = if name_first {
+        (left, right)
+      } else {
+        (right, left)
+      };
+      if name.is_empty() {
+        return None;
+      }
+      Version::parse(version)
+        .ok()
+        .map(|version| (name.to_string(), version))
+    })
+  }
+}
+
+impl Default for TagPattern {
+  fn default() -> TagPattern {
+    TagPattern {
+      template: TagPattern::DEFAULT.to_string(),
+    }
+  }
+}
+
+impl FromStr for TagPattern {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<TagPattern> {
+    let invalid =
+      |key: &str| -> crate::Result<TagPattern> { Err(ErrorKind::Config(t!(key, s)).into()) };
+    if s.matches("{version}").count() != 1 {
+      return invalid("changelog.tag_pattern_version");
+    }
+    if s.matches("{name}").count() > 1 {
+      return invalid("changelog.tag_pattern_name");
+    }
+    if s.contains("{name}{version}") || s.contains("{version}{name}") {
+      return invalid("changelog.tag_pattern_separate");
+    }
+    Ok(TagPattern {
+      template: s.to_string(),
+    })
+  }
+}
+
+/// The timezone release dates are shown in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timezone {
+  Utc,
+  Local,
+  /// The offset recorded by the tagger or the committer.
+  Original,
+  Fixed(FixedOffset),
+}
+
+impl FromStr for Timezone {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<Timezone> {
+    match s {
+      "utc" => Ok(Timezone::Utc),
+      "local" => Ok(Timezone::Local),
+      "original" => Ok(Timezone::Original),
+      _ => s
+        .parse()
+        .map(Timezone::Fixed)
+        .map_err(|_| ErrorKind::Config(t!("changelog.timezone_invalid", s)).into()),
+    }
+  }
+}
+
+/// How release dates are formatted.
+#[derive(Clone, Debug)]
+pub struct DateOptions {
+  format: String,
+  timezone: Timezone,
+}
+
+impl DateOptions {
+  /// The format used by the generated changelogs so far.
+  pub const DEFAULT_FORMAT: &'static str = "%Y-%m-%d";
+
+  /// Create the options, `format` uses the `strftime` syntax.
+  pub fn new(format: &str, timezone: Timezone) -> crate::Result<DateOptions> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+      return Err(ErrorKind::Config(t!("changelog.date_format_invalid", format)).into());
+    }
+    Ok(DateOptions {
+      format: format.to_string(),
+      timezone,
+    })
+  }
+
+  /// Format a git timestamp.
+  #[must_use]
+  pub fn format(&self, time: git2::Time) -> String {
+    let datetime = DateTime::from_timestamp(time.seconds(), 0).expect("Invalid timestamp");
+    let format = self.format.as_str();
+    match self.timezone {
+      Timezone::Utc => datetime.format(format).to_string(),
+      Timezone::Local => datetime.with_timezone(&Local).format(format).to_string(),
+      Timezone::Original => {
+        let offset = FixedOffset::east_opt(time.offset_minutes() * 60).expect("Invalid offset");
+        datetime.with_timezone(&offset).format(format).to_string()
+      }
+      Timezone::Fixed(offset) => datetime.with_timezone(&offset).format(format).to_string(),
+    }
+  }
+}
+
+impl Default for DateOptions {
+  fn default() -> DateOptions {
+    DateOptions {
+      format: DateOptions::DEFAULT_FORMAT.to_string(),
+      timezone: Timezone::Utc,
+    }
+  }
+}
+
+/// Options used when turning tags into releases.
+#[derive(Clone, Debug)]
+pub struct ReleaseOptions {
+  pub prerelease: PrereleasePolicy,
+  pub latest: LatestRelease,
+  pub tag_pattern: TagPattern,
+  pub date: DateOptions,
+}
+
+impl Default for ReleaseOptions {
+  fn default() -> ReleaseOptions {
+    ReleaseOptions {
+      prerelease: PrereleasePolicy::Include,
+      latest: LatestRelease::Any,
+      tag_pattern: TagPattern::default(),
+      date: DateOptions::default(),
+    }
+  }
 }
 
 /// A git commit.
@@ -49,9 +385,21 @@ pub struct Commit {
   hash: String,
   author: Option<String>,
   datetime: DateTime<Utc>,
+  paths: Vec<String>,
 }
 
 impl Commit {
+  #[cfg(test)]
+  pub(crate) fn new(message: &str, hash: &str, author: &str) -> Commit {
+    Commit {
+      message: message.to_string(),
+      hash: hash.to_string(),
+      author: Some(author.to_string()),
+      datetime: DateTime::from_timestamp(0, 0).unwrap(),
+      paths: vec![],
+    }
+  }
+
   /// Access the commit message.
   #[inline]
   #[must_use]
@@ -79,300 +427,1083 @@ impl Commit {
   pub fn datetime(&self) -> &DateTime<Utc> {
     &self.datetime
   }
+
+  /// Access the paths changed by the commit, empty unless
+  /// [`WalkOptions::collect_paths`] is set.
+  #[inline]
+  #[must_use]
+  pub fn paths(&self) -> &[String] {
+    &self.paths
+  }
 }
 
-/// Diff two git objects.
-pub fn diff(repo: &Repository, o1: git2::Commit, o2: git2::Commit) -> crate::Result<String> {
-  let t1 = o1.tree().context(ErrorKind::Git)?;
-  let tree2 = o2.tree().context(ErrorKind::Git)?;
-  // If o2 is the first object then we want to include it in the diff
-  // so we diff o1 with None
-  let t2 = match o2.parent(0) {
+/// List the paths a commit changes compared to its first parent.
+pub fn commit_paths(repo: &Repository, commit: &git2::Commit) -> crate::Result<Vec<String>> {
+  let tree = commit.tree().context(ErrorKind::Git)?;
+  let parent_tree = match commit.parent(0) {
+    Ok(parent) => Some(parent.tree().context(ErrorKind::Git)?),
     Err(_err) => None,
-    Ok(_parent) => Some(&tree2),
   };
   let diff = repo
-    .diff_tree_to_tree(t2, Some(&t1), None)
+    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+    .context(ErrorKind::Git)?;
+  Ok(
+    diff
+      .deltas()
+      .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+      .flatten()
+      .map(|path| path.to_string_lossy().replace('\\', "/"))
+      .collect::<std::collections::BTreeSet<_>>()
+      .into_iter()
+      .collect(),
+  )
+}
+
+/// Diff two git objects. Without `o2` the diff starts from an empty tree,
+/// so the first commit is included.
+pub fn diff(
+  repo: &Repository,
+  o1: git2::Commit,
+  o2: Option<git2::Commit>,
+) -> crate::Result<String> {
+  let t1 = o1.tree().context(ErrorKind::Git)?;
+  let t2 = match o2 {
+    Some(o2) => Some(o2.tree().context(ErrorKind::Git)?),
+    None => None,
+  };
+  let diff = repo
+    .diff_tree_to_tree(t2.as_ref(), Some(&t1), None)
     .context(ErrorKind::Git)?;
   let stats = diff.stats().context(ErrorKind::Git)?;
   let format = DiffStatsFormat::FULL;
   let buf = stats.to_buf(format, 80).context(ErrorKind::Git)?;
-  let buf = str::from_utf8(&*buf).context(ErrorKind::Other)?;
+  let buf = str::from_utf8(&buf).context(ErrorKind::Other)?;
   Ok(buf.to_owned())
 }
 
-/**
- * 获取 tag 和 version
- */
-pub fn get_version(tag: &str) -> TagAndVersion {
-  let package_list = tag.split('@').collect::<Vec<&str>>();
-  let tag = TagAndVersion {
-    package: "@".to_owned() + &package_list.get(1).unwrap().to_string(),
-    version: package_list.last().unwrap().to_string(),
-  };
-  tag
+/// List the tags of a package with their versions, sorted from the oldest
+/// to the newest version and filtered by the prerelease policy.
+fn get_tag_list(
+  repo: &Repository,
+  package_name: &str,
+  options: &ReleaseOptions,
+) -> crate::Result<Vec<(String, Version)>> {
+  Ok(filter_tag_list(
+    &get_tag_names(repo)?,
+    package_name,
+    options,
+  ))
 }
 
-/**
- * 排序 Tag，根据tag中带的版本号
- */
-fn sort_tags<'a>(tags: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
-  let mut tags = tags
+/// List the names of all tags in the repository.
+fn get_tag_names(repo: &Repository) -> crate::Result<Vec<String>> {
+  Ok(
+    repo
+      .tag_names(None)
+      .context(ErrorKind::Git)?
+      .into_iter()
+      .flatten()
+      .map(str::to_string)
+      .collect(),
+  )
+}
+
+/// Pick the tags of a package out of `tag_names`, see [`get_tag_list`].
+fn filter_tag_list(
+  tag_names: &[String],
+  package_name: &str,
+  options: &ReleaseOptions,
+) -> Vec<(String, Version)> {
+  let mut tag_list = tag_names
+    .iter()
     .filter_map(|tag| {
-      Version::parse(&get_version(tag).to_owned().version)
-        .ok()
-        .map(|version| (tag, version))
+      options
+        .tag_pattern
+        .version(tag, package_name)
+        .map(|version| (tag.clone(), version))
     })
     .collect::<Vec<_>>();
 
-  tags.sort_by(|(_, a), (_, b)| a.cmp(b));
+  tag_list.sort_by(|(_, a), (_, b)| a.cmp(b));
 
-  let tags = tags.into_iter().map(|(tag, _)| tag).collect();
+  match options.prerelease {
+    PrereleasePolicy::Include => {}
+    PrereleasePolicy::Exclude => tag_list.retain(|(_, version)| version.pre.is_empty()),
+    PrereleasePolicy::RollUp => {
+      // Prereleases older than the latest stable release were shipped in it.
+      let latest_stable = tag_list
+        .iter()
+        .rposition(|(_, version)| version.pre.is_empty());
+      if let Some(latest_stable) = latest_stable {
+        let unreleased = tag_list.split_off(latest_stable + 1);
+        tag_list.retain(|(_, version)| version.pre.is_empty());
+        tag_list.extend(unreleased);
+      }
+    }
+  }
+  tag_list
+}
 
-  tags
+/// Describe the tag of a release. Annotated tags use the tagger date and
+/// their message as summary, everything else uses the commit date.
+fn get_tag(repo: &Repository, name: &str, commit: &git2::Commit, date: &DateOptions) -> Tag {
+  let annotated = repo
+    .find_reference(&format!("refs/tags/{}", name))
+    .and_then(|reference| reference.peel_to_tag())
+    .ok();
+  let time = annotated
+    .as_ref()
+    .and_then(|tag| tag.tagger())
+    .map(|tagger| tagger.when())
+    .unwrap_or_else(|| commit.time());
+  let summary = annotated
+    .as_ref()
+    .and_then(|tag| tag.message())
+    .map(str::trim)
+    .filter(|message| !message.is_empty())
+    .map(str::to_string);
+  Tag {
+    name: name.to_string(),
+    date_time: date.format(time),
+    summary,
+  }
 }
 
-fn get_tag_list<'a>(repo: &'a Repository, package_name: &'a str) -> Vec<String> {
-  let mut tag_list = repo
-    .tag_names(None)
-    .context(ErrorKind::Git)
-    .unwrap()
-    .into_iter()
-    .filter_map(|x| x)
-    .filter(|x| x.starts_with(package_name))
-    .filter_map(|tag| {
-      Version::parse(&get_version(tag).to_owned().version)
-        .ok()
-        .map(|version| (tag.to_string(), version))
-    })
-    .collect::<Vec<_>>();
+/// Build the releases for sorted tags, each range ending at the tag before
+/// it. The first tag's range reaches back to the first commit.
+fn get_release_list<'r>(
+  repo: &'r Repository,
+  tag_list: Vec<(String, Version)>,
+  options: &ReleaseOptions,
+) -> crate::Result<Vec<Release<'r>>> {
+  let mut release_list: Vec<Release> = vec![];
+  let mut previous: Option<git2::Commit> = None;
 
-  tag_list.sort_by(|(_, a), (_, b)| a.cmp(b));
+  for (name, version) in tag_list {
+    let start = repo
+      .revparse_single(&name)
+      .context(ErrorKind::Git)?
+      .peel_to_commit()
+      .context(ErrorKind::Git)?;
+    let latest_tag = get_tag(repo, &name, &start, &options.date);
+    let end = previous.replace(start.clone());
+    release_list.push(Release {
+      version,
+      range: CommitRange {
+        latest_tag,
+        start,
+        end,
+      },
+    });
+  }
 
-  let tags = tag_list
-    .iter()
-    .map(|(tag, _)| tag.clone())
-    .collect::<Vec<String>>();
+  Ok(release_list)
+}
 
-  tag_list.clear();
+/// Get every release of a package ordered from the oldest to the newest,
+/// including the initial release. A package without tags has no releases.
+pub fn tag_history<'r>(
+  repo: &'r Repository,
+  package_name: &str,
+  options: &ReleaseOptions,
+) -> crate::Result<Vec<Release<'r>>> {
+  get_release_list(repo, get_tag_list(repo, package_name, options)?, options)
+}
 
-  tags
+/// Get the [`tag_history`] of several packages, listing the tags only once.
+pub fn package_tag_history<'r>(
+  repo: &'r Repository,
+  package_names: &[String],
+  options: &ReleaseOptions,
+) -> crate::Result<Vec<Vec<Release<'r>>>> {
+  let tag_names = get_tag_names(repo)?;
+  package_names
+    .iter()
+    .map(|package_name| {
+      get_release_list(
+        repo,
+        filter_tag_list(&tag_names, package_name, options),
+        options,
+      )
+    })
+    .collect()
 }
 
 /// 获取commit 的范围，默认获取的是 latest
 pub fn get_commit_latest_range<'r>(
   repo: &'r Repository,
   package_name: &str,
+  options: &ReleaseOptions,
 ) -> crate::Result<CommitRange<'r>> {
-  let tag_list = repo.tag_names(None).context(ErrorKind::Git)?;
+  let mut tag_list = get_tag_list(repo, package_name, options)?
+    .into_iter()
+    .filter(|(tag, _)| !tag.contains("experimental"))
+    .collect::<Vec<_>>();
+  if options.latest == LatestRelease::Stable {
+    let latest_stable = tag_list
+      .iter()
+      .rposition(|(_, version)| version.pre.is_empty())
+      .ok_or(ErrorKind::NoTags)?;
+    tag_list.truncate(latest_stable + 1);
+  }
+  // Only the latest release and the one before it are needed.
+  tag_list.drain(..tag_list.len().saturating_sub(2));
 
-  let tags = sort_tags(
-    tag_list
-      .into_iter()
-      .filter_map(|x| x)
-      .filter(|x| x.starts_with(package_name) && !x.contains("experimental")),
-  );
-  let len = tags.len();
-
-  let (start, end) = match len {
-    0 => return Err(ErrorKind::NoTags.into()),
-    1 => (tags.get(len - 1), None),
-    _ => (tags.get(len - 1), tags.get(len - 2)),
-  };
+  get_release_list(repo, tag_list, options)?
+    .pop()
+    .map(|release| release.range)
+    .ok_or_else(|| ErrorKind::NoTags.into())
+}
 
-  println!("commit range: {:?} - {:?}", start, end);
-
-  // Value has to be `Some()` here.
-  let start_str = start.expect("Tag should have a value.");
-  let (start, end) = match (start_str, end) {
-    (start, None) => {
-      let start = repo.revparse_single(start).context(ErrorKind::Git)?;
-      let mut reveals = repo.revwalk().context(ErrorKind::Git)?;
-      reveals.push(start.id()).context(ErrorKind::Git)?;
-      let oid = reveals
-        .nth(0)
-        .ok_or(ErrorKind::Git)?
-        .context(ErrorKind::Git)?;
-      let last = repo.find_object(oid, None).unwrap();
-      (start, last)
-    }
-    (start, Some(end)) => (
-      repo.revparse_single(start).context(ErrorKind::Git)?,
-      repo.revparse_single(end).context(ErrorKind::Git)?,
-    ),
+/// Get the commits reachable from `head` that are not part of the latest
+/// release yet. Without any release the range reaches back to the first
+/// commit.
+pub fn get_unreleased_range<'r>(
+  repo: &'r Repository,
+  package_name: &str,
+  head: &str,
+  options: &ReleaseOptions,
+) -> crate::Result<CommitRange<'r>> {
+  let start = repo
+    .revparse_single(head)
+    .context(ErrorKind::Git)?
+    .peel_to_commit()
+    .context(ErrorKind::Git)?;
+  let end = match get_commit_latest_range(repo, package_name, options) {
+    Ok(latest_range) => Some(latest_range.start),
+    Err(err) => match err.kind() {
+      ErrorKind::NoTags => None,
+      _ => return Err(err),
+    },
   };
-
-  let cr = CommitRange {
-    start: start
-      .peel_to_commit()
-      .expect("There's no commit at the start point"),
-    end: end
-      .peel_to_commit()
-      .expect("There's no commit at the end point"),
+  Ok(CommitRange {
     latest_tag: Tag {
-      date_time: DateTime::from_timestamp(
-        start
-          .peel_to_commit()
-          .expect("There's no commit at the start point")
-          .time()
-          .seconds(),
-        0,
-      )
-      .expect("There's no commit at the start point")
-      .format("%Y-%m-%d")
-      .to_string(),
-      name: ((*start_str).to_owned()),
+      name: UNRELEASED.to_string(),
+      date_time: options.date.format(start.time()),
+      summary: None,
     },
+    start,
+    end,
+  })
+}
+
+/// Get the commits reachable from `to` but not from `from`. Both can be any
+/// ref such as a branch, a tag or a commit.
+pub fn get_ref_range<'r>(
+  repo: &'r Repository,
+  from: &str,
+  to: &str,
+  options: &ReleaseOptions,
+) -> crate::Result<CommitRange<'r>> {
+  let peel = |spec: &str| {
+    repo
+      .revparse_single(spec)
+      .context(ErrorKind::Git)?
+      .peel_to_commit()
+      .context(ErrorKind::Git)
+  };
+  let start = peel(to)?;
+  let end = peel(from)?;
+  Ok(CommitRange {
+    latest_tag: get_tag(repo, to, &start, &options.date),
+    start,
+    end: Some(end),
+  })
+}
+
+/// Compute the statistics of the changes between the ends of a range,
+/// optionally restricted to a path.
+pub fn diff_stats(
+  repo: &Repository,
+  commit_range: &CommitRange,
+  path: Option<&str>,
+) -> crate::Result<DiffStats> {
+  let new_tree = commit_range.start.tree().context(ErrorKind::Git)?;
+  let old_tree = match &commit_range.end {
+    Some(end) => Some(end.tree().context(ErrorKind::Git)?),
+    None => None,
+  };
+  let mut diff_options = DiffOptions::new();
+  if let Some(path) = path {
+    diff_options.pathspec(path);
+  }
+  let diff = repo
+    .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), Some(&mut diff_options))
+    .context(ErrorKind::Git)?;
+
+  let mut path_stats = vec![];
+  for index in 0..diff.deltas().len() {
+    let patch = match Patch::from_diff(&diff, index).context(ErrorKind::Git)? {
+      Some(patch) => patch,
+      None => continue,
+    };
+    let delta = patch.delta();
+    let file_path = delta
+      .new_file()
+      .path()
+      .or_else(|| delta.old_file().path())
+      .map(|file_path| file_path.display().to_string())
+      .unwrap_or_default();
+    let (_, insertions, deletions) = patch.line_stats().context(ErrorKind::Git)?;
+    path_stats.push(PathStats {
+      path: file_path,
+      insertions,
+      deletions,
+    });
+  }
+
+  let mut stats = DiffStats {
+    files_changed: path_stats.len(),
+    insertions: path_stats.iter().map(|stats| stats.insertions).sum(),
+    deletions: path_stats.iter().map(|stats| stats.deletions).sum(),
+    top_paths: vec![],
   };
-  Ok(cr)
+  path_stats.sort_by(|a, b| {
+    (b.insertions + b.deletions)
+      .cmp(&(a.insertions + a.deletions))
+      .then_with(|| a.path.cmp(&b.path))
+  });
+  path_stats.truncate(TOP_PATHS);
+  stats.top_paths = path_stats;
+  Ok(stats)
 }
 
 /// Get the full diff in a single convenience function.
 pub fn latest_diff(path: &str, package_name: &str) -> crate::Result<String> {
   let repo = Repository::open(path).context(ErrorKind::Git)?;
-  let commit_range = get_commit_latest_range(&repo, package_name)?;
+  let commit_range = get_commit_latest_range(&repo, package_name, &ReleaseOptions::default())?;
+  diff(&repo, commit_range.start, commit_range.end)
+}
+
+pub fn get_commit_list_by_commit_range(
+  repo: &Repository,
+  commit_range: CommitRange,
+  options: &WalkOptions,
+) -> crate::Result<Vec<Commit>> {
   let start = commit_range.start;
   let end = commit_range.end;
-  Ok(diff(&repo, start, end)?)
+
+  // Walk `end..start`, hiding everything reachable from the previous
+  // release so that merges and tags on other branches don't pull in
+  // unrelated history.
+  let mut revwalk = repo.revwalk().context(ErrorKind::Git)?;
+  revwalk
+    .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+    .context(ErrorKind::Git)?;
+  if options.first_parent {
+    revwalk.simplify_first_parent().context(ErrorKind::Git)?;
+  }
+  revwalk.push(start.id()).context(ErrorKind::Git)?;
+  if let Some(end) = end {
+    revwalk.hide(end.id()).context(ErrorKind::Git)?;
+  }
+
+  let mut commits = vec![];
+  for id in revwalk {
+    let commit = repo
+      .find_commit(id.context(ErrorKind::Git)?)
+      .context(ErrorKind::Git)?;
+    if options.no_merges && commit.parent_count() > 1 {
+      continue;
+    }
+    commits.push(to_commit(repo, &commit, options)?);
+  }
+
+  Ok(commits)
 }
 
-pub fn get_all_tag_range<'r>(
-  repo: &'r Repository,
+/// Read the details of a commit.
+pub(crate) fn to_commit(
+  repo: &Repository,
+  commit: &git2::Commit,
+  options: &WalkOptions,
+) -> crate::Result<Commit> {
+  let message = commit.message().ok_or(ErrorKind::Git)?.to_string();
+
+  let hash = format!("{}", commit.id());
+  let author = commit.author().name().map(|name| name.to_owned());
+  let timestamp = commit.time().seconds();
+  let datetime = DateTime::from_timestamp(timestamp, 0).expect("Invalid timestamp");
+  let paths = if options.collect_paths {
+    commit_paths(repo, commit)?
+  } else {
+    vec![]
+  };
+  Ok(Commit {
+    message,
+    hash,
+    author,
+    datetime,
+    paths,
+  })
+}
+
+/// The oldest commit of every author in the history of `HEAD` and the tags,
+/// hashes by author name.
+pub fn first_commits(repo: &Repository) -> crate::Result<HashMap<String, String>> {
+  let mut revwalk = repo.revwalk().context(ErrorKind::Git)?;
+  revwalk
+    .set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)
+    .context(ErrorKind::Git)?;
+  if repo.head().is_ok() {
+    revwalk.push_head().context(ErrorKind::Git)?;
+  }
+  revwalk.push_glob("refs/tags").context(ErrorKind::Git)?;
+
+  let mut first_commits = HashMap::new();
+  for id in revwalk {
+    let id = id.context(ErrorKind::Git)?;
+    let commit = repo.find_commit(id).context(ErrorKind::Git)?;
+    let author = commit.author();
+    if let Some(name) = author.name() {
+      first_commits
+        .entry(name.to_owned())
+        .or_insert_with(|| id.to_string());
+    }
+  }
+  Ok(first_commits)
+}
+
+/// Compute the diff statistics of a range when [`WalkOptions::diff_stats`]
+/// is enabled.
+pub(crate) fn range_stats(
+  repo: &Repository,
+  commit_range: &CommitRange,
+  options: &WalkOptions,
+) -> crate::Result<Option<DiffStats>> {
+  if !options.diff_stats {
+    return Ok(None);
+  }
+  diff_stats(repo, commit_range, options.stats_path.as_deref()).map(Some)
+}
+
+/// Collect the commits of a range, and its diff statistics when enabled.
+fn get_tag_and_commit(
+  repo: &Repository,
+  commit_range: CommitRange,
+  options: &WalkOptions,
+) -> crate::Result<TagAndCommit> {
+  let tag = commit_range.latest_tag.clone();
+  let stats = range_stats(repo, &commit_range, options)?;
+  let commit_list = get_commit_list_by_commit_range(repo, commit_range, options)?;
+  Ok(TagAndCommit {
+    tag,
+    commit_list,
+    stats,
+  })
+}
+
+pub fn latest_commits(
+  repo: &Repository,
+  package_name: &str,
+  release_options: &ReleaseOptions,
+  options: &WalkOptions,
+) -> crate::Result<TagAndCommit> {
+  let commit_range = get_commit_latest_range(repo, package_name, release_options)?;
+  get_tag_and_commit(repo, commit_range, options)
+}
+
+/// Get the commits from `head` back to the latest release, tagged as
+/// [`UNRELEASED`].
+pub fn unreleased_commits(
+  repo: &Repository,
+  package_name: &str,
+  head: &str,
+  release_options: &ReleaseOptions,
+  options: &WalkOptions,
+) -> crate::Result<TagAndCommit> {
+  let commit_range = get_unreleased_range(repo, package_name, head, release_options)?;
+  get_tag_and_commit(repo, commit_range, options)
+}
+
+/// Get the commits between two refs, tagged with the name of `to`. Use
+/// [`package_ref_range_commits`] for several packages so the range is
+/// walked only once.
+pub fn ref_range_commits(
+  repo: &Repository,
+  from: &str,
+  to: &str,
+  release_options: &ReleaseOptions,
+  options: &WalkOptions,
+) -> crate::Result<TagAndCommit> {
+  let commit_range = get_ref_range(repo, from, to, release_options)?;
+  get_tag_and_commit(repo, commit_range, options)
+}
+
+/// Get the commits between two refs for every package. The range is walked
+/// once with the options of the first package, the diff statistics use the
+/// options of each package.
+pub fn package_ref_range_commits(
+  repo: &Repository,
+  from: &str,
+  to: &str,
+  package_names: &[String],
+  release_options: &ReleaseOptions,
+  package_options: impl Fn(&str) -> WalkOptions,
+) -> crate::Result<Vec<TagAndCommit>> {
+  let commit_range = get_ref_range(repo, from, to, release_options)?;
+  let options = package_names
+    .first()
+    .map(|package_name| package_options(package_name))
+    .unwrap_or_default();
+  let commit_list = get_commit_list_by_commit_range(repo, commit_range.clone(), &options)?;
+
+  package_names
+    .iter()
+    .map(|package_name| {
+      Ok(TagAndCommit {
+        tag: commit_range.latest_tag.clone(),
+        commit_list: commit_list.clone(),
+        stats: range_stats(repo, &commit_range, &package_options(package_name))?,
+      })
+    })
+    .collect()
+}
+
+/// Get the commits of every release, from the newest to the oldest. Use
+/// [`package_full_commits`] for several packages so the history is walked
+/// only once.
+pub fn full_commits(
+  repo: &Repository,
   package_name: &str,
-) -> crate::Result<Vec<CommitRange<'r>>> {
-  let mut cr_list: Vec<CommitRange> = vec![];
+  release_options: &ReleaseOptions,
+  options: &WalkOptions,
+) -> crate::Result<Vec<TagAndCommit>> {
+  let package_names = [package_name.to_string()];
+  let mut package_list =
+    package_full_commits(repo, &package_names, release_options, |_| options.clone())?;
+  Ok(package_list.pop().unwrap_or_default())
+}
 
-  let tags = get_tag_list(repo, package_name);
+#[cfg(test)]
+pub(crate) mod tests {
+  use git2::{Commit as GitCommit, Index, IndexEntry, IndexTime, Oid, Repository, Signature, Time};
+  use tempfile::TempDir;
 
-  let len = tags.len();
+  use super::{
+    commit_paths, diff_stats, first_commits, get_commit_latest_range,
+    get_commit_list_by_commit_range, get_ref_range, get_unreleased_range,
+    package_ref_range_commits, tag_history, CommitRange, DateOptions, LatestRelease,
+    PrereleasePolicy, ReleaseOptions, Tag, TagPattern, Timezone, WalkOptions,
+  };
+  use semver::Version;
 
-  while cr_list.len() < len - 1 {
-    let cr_list_len = cr_list.len();
-    let (start, end) = match len {
-      0 => return Err(ErrorKind::NoTags.into()),
-      1 => (tags.get(len - cr_list_len - 1), None),
-      _ => (
-        tags.get(len - cr_list_len - 1),
-        tags.get(len - cr_list_len - 2),
-      ),
-    };
+  pub(crate) struct TestRepo {
+    _dir: TempDir,
+    repo: Repository,
+    time: i64,
+  }
 
-    // Value has to be `Some()` here.
-    let start_str = start.expect("Tag should have a value.");
-    let (start, end) = match (start_str, end) {
-      (start, None) => {
-        let start = repo.revparse_single(start).context(ErrorKind::Git)?;
-        let mut reveals = repo.revwalk().context(ErrorKind::Git)?;
-        reveals.push(start.id()).context(ErrorKind::Git)?;
-        let oid = reveals
-          .nth(0)
-          .ok_or(ErrorKind::Git)?
-          .context(ErrorKind::Git)?;
-        let last = repo.find_object(oid, None).unwrap();
-        (start, last)
+  impl TestRepo {
+    pub(crate) fn new() -> TestRepo {
+      let dir = tempfile::tempdir().unwrap();
+      let repo = Repository::init(dir.path()).unwrap();
+      TestRepo {
+        _dir: dir,
+        repo,
+        time: 1_600_000_000,
       }
-      (start, Some(end)) => (
-        repo.revparse_single(start).context(ErrorKind::Git)?,
-        repo.revparse_single(end).context(ErrorKind::Git)?,
-      ),
+    }
+
+    /// The working directory, where changelogs look for workspace packages.
+    pub(crate) fn path(&self) -> String {
+      self.repo.workdir().unwrap().display().to_string()
+    }
+
+    pub(crate) fn commit(&mut self, parents: &[Oid], message: &str) -> Oid {
+      self.commit_as(parents, message, "doctor")
+    }
+
+    pub(crate) fn commit_as(&mut self, parents: &[Oid], message: &str, author: &str) -> Oid {
+      self.time += 60;
+      let signature = Signature::new(
+        author,
+        &format!("{}@example.com", author),
+        &Time::new(self.time, 0),
+      )
+      .unwrap();
+      let parents: Vec<GitCommit> = parents
+        .iter()
+        .map(|id| self.repo.find_commit(*id).unwrap())
+        .collect();
+      let base_tree = parents.first().map(|parent| parent.tree().unwrap());
+      let mut builder = self.repo.treebuilder(base_tree.as_ref()).unwrap();
+      let blob = self.repo.blob(message.as_bytes()).unwrap();
+      builder
+        .insert(format!("{}.txt", self.time), blob, 0o100644)
+        .unwrap();
+      let tree = self.repo.find_tree(builder.write().unwrap()).unwrap();
+      let parent_refs: Vec<&GitCommit> = parents.iter().collect();
+      self
+        .repo
+        .commit(None, &signature, &signature, message, &tree, &parent_refs)
+        .unwrap()
+    }
+
+    fn commit_file(&mut self, parent: Oid, path: &str, content: &str) -> Oid {
+      self.commit_change(parent, path, path, content)
+    }
+
+    pub(crate) fn commit_change(
+      &mut self,
+      parent: Oid,
+      message: &str,
+      path: &str,
+      content: &str,
+    ) -> Oid {
+      self.time += 60;
+      let signature =
+        Signature::new("doctor", "doctor@example.com", &Time::new(self.time, 0)).unwrap();
+      let parent = self.repo.find_commit(parent).unwrap();
+      let mut index = Index::new().unwrap();
+      index.read_tree(&parent.tree().unwrap()).unwrap();
+      let blob = self.repo.blob(content.as_bytes()).unwrap();
+      let entry = IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: content.len() as u32,
+        id: blob,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+      };
+      index.add(&entry).unwrap();
+      let tree = self
+        .repo
+        .find_tree(index.write_tree_to(&self.repo).unwrap())
+        .unwrap();
+      self
+        .repo
+        .commit(None, &signature, &signature, message, &tree, &[&parent])
+        .unwrap()
+    }
+
+    pub(crate) fn tag(&self, id: Oid, name: &str) {
+      let commit = self.repo.find_commit(id).unwrap();
+      self
+        .repo
+        .tag_lightweight(name, commit.as_object(), false)
+        .unwrap();
+    }
+
+    fn range(&self, start: Oid, end: Option<Oid>) -> CommitRange<'_> {
+      CommitRange {
+        latest_tag: Tag {
+          name: "test".to_string(),
+          date_time: "".to_string(),
+          summary: None,
+        },
+        start: self.repo.find_commit(start).unwrap(),
+        end: end.map(|end| self.repo.find_commit(end).unwrap()),
+      }
+    }
+
+    fn messages(&self, start: Oid, end: Option<Oid>, options: &WalkOptions) -> Vec<String> {
+      let mut messages: Vec<String> =
+        get_commit_list_by_commit_range(&self.repo, self.range(start, end), options)
+          .unwrap()
+          .iter()
+          .map(|commit| commit.message().to_string())
+          .collect();
+      messages.sort();
+      messages
+    }
+  }
+
+  #[test]
+  fn it_finds_first_commits() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let first = repo.commit_as(&[root], "feat: a", "someone");
+    let second = repo.commit_as(&[first], "feat: b", "someone");
+    let branch = repo.commit_as(&[root], "feat: c", "newcomer");
+    repo.commit_as(&[second], "feat: d", "stranger");
+    repo.tag(second, "v1.0.0");
+    repo.tag(branch, "v0.1.0");
+
+    let first_commits = first_commits(&repo.repo).unwrap();
+    assert_eq!(first_commits["doctor"], root.to_string());
+    assert_eq!(first_commits["someone"], first.to_string());
+    assert_eq!(first_commits["newcomer"], branch.to_string());
+    assert!(!first_commits.contains_key("stranger"));
+  }
+
+  #[test]
+  fn it_walks_merge_ranges() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let v1 = repo.commit(&[root], "feat: v1");
+    let main = repo.commit(&[v1], "fix: on main");
+    let feature = repo.commit(&[v1], "feat: on branch");
+    let v2 = repo.commit(&[main, feature], "Merge branch feature");
+
+    assert_eq!(
+      repo.messages(v2, Some(v1), &WalkOptions::default()),
+      vec!["Merge branch feature", "feat: on branch", "fix: on main"]
+    );
+
+    let first_parent = WalkOptions {
+      first_parent: true,
+      ..WalkOptions::default()
     };
+    assert_eq!(
+      repo.messages(v2, Some(v1), &first_parent),
+      vec!["Merge branch feature", "fix: on main"]
+    );
 
-    let cr = CommitRange {
-      start: start
-        .peel_to_commit()
-        .expect("There's no commit at the start point"),
-      end: end
-        .peel_to_commit()
-        .expect("There's no commit at the end point"),
-      latest_tag: Tag {
-        date_time: DateTime::from_timestamp(
-          start
-            .peel_to_commit()
-            .expect("There's no commit at the start point")
-            .time()
-            .seconds(),
-          0,
-        )
-        .expect("There's no commit at the start point")
-        .format("%Y-%m-%d")
-        .to_string(),
-        name: ((*start_str).to_owned()),
-      },
+    let no_merges = WalkOptions {
+      no_merges: true,
+      ..WalkOptions::default()
     };
-    cr_list.insert(cr_list.len(), cr);
+    assert_eq!(
+      repo.messages(v2, Some(v1), &no_merges),
+      vec!["feat: on branch", "fix: on main"]
+    );
   }
 
-  Ok(cr_list)
-}
+  #[test]
+  fn it_walks_tags_on_different_branches() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let base = repo.commit(&[root], "feat: base");
+    // 1.0.1 is a hotfix tagged on a branch that never got merged back
+    let hotfix = repo.commit(&[base], "fix: hotfix");
+    let next = repo.commit(&[base], "feat: next");
+    let v1_1 = repo.commit(&[next], "feat: 1.1");
 
-pub fn get_commit_list_by_commit_range(
-  repo: &Repository,
-  commit_range: CommitRange,
-) -> crate::Result<Vec<Commit>> {
-  let start = commit_range.start;
-  let end = commit_range.end;
+    assert_eq!(
+      repo.messages(v1_1, Some(hotfix), &WalkOptions::default()),
+      vec!["feat: 1.1", "feat: next"]
+    );
+    // Ranges without a previous release include the whole history.
+    assert_eq!(
+      repo.messages(base, None, &WalkOptions::default()),
+      vec!["feat: base", "root"]
+    );
+  }
 
-  let end_is_first_commit = match end.parent(0) {
-    Err(_err) => true,
-    _ => false,
-  };
+  #[test]
+  fn it_builds_tag_history() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    assert!(tag_history(
+      &repo.repo,
+      "@ant-design/pro-form",
+      &ReleaseOptions::default()
+    )
+    .unwrap()
+    .is_empty());
 
-  let mut revwalk = repo.revwalk().context(ErrorKind::Git)?;
-  revwalk.push(start.id()).context(ErrorKind::Git)?;
-  let revwalk = revwalk.filter_map(|id| repo.find_commit(id.ok()?).ok());
+    repo.tag(root, "@ant-design/pro-form@1.0.0");
+    let fix = repo.commit(&[root], "fix: form");
+    repo.tag(fix, "@ant-design/pro-form@1.10.0");
+    let feat = repo.commit(&[fix], "feat: form");
+    repo.tag(feat, "@ant-design/pro-form@1.2.0");
+    repo.tag(feat, "@ant-design/pro-table@1.0.0");
 
-  let mut commits = vec![];
-  for commit in revwalk {
-    if end.id() == commit.id() && !end_is_first_commit {
-      break;
+    let history = tag_history(
+      &repo.repo,
+      "@ant-design/pro-form",
+      &ReleaseOptions::default(),
+    )
+    .unwrap();
+    let versions: Vec<String> = history
+      .iter()
+      .map(|release| release.version.to_string())
+      .collect();
+    assert_eq!(versions, vec!["1.0.0", "1.2.0", "1.10.0"]);
+
+    let initial = &history[0].range;
+    assert_eq!(initial.start.id(), root);
+    assert!(initial.end.is_none());
+    assert_eq!(
+      history[2].range.end.as_ref().map(|end| end.id()),
+      Some(feat)
+    );
+  }
+
+  #[test]
+  fn it_applies_prerelease_policy() {
+    let mut repo = TestRepo::new();
+    let mut parent = repo.commit(&[], "root");
+    for version in ["1.0.0", "1.1.0-beta.1", "1.1.0", "1.2.0-alpha.0"] {
+      parent = repo.commit(&[parent], version);
+      repo.tag(parent, &format!("@ant-design/pro-form@{}", version));
     }
-    let message = commit.message().ok_or(ErrorKind::Git)?.to_string();
-
-    let hash = format!("{}", commit.id());
-    let author = commit.author().name().map(|name| name.to_owned());
-    let timestamp = commit.time().seconds();
-    let naive_datetime = DateTime::from_timestamp(timestamp, 0)
-      .expect("Invalid timestamp")
-      .naive_utc();
-    let datetime: DateTime<Utc> = DateTime::from_naive_utc_and_offset(naive_datetime, Utc);
-    commits.push(Commit {
-      message,
-      hash,
-      author,
-      datetime,
-    });
+
+    let versions = |prerelease| -> Vec<String> {
+      let options = ReleaseOptions {
+        prerelease,
+        ..ReleaseOptions::default()
+      };
+      tag_history(&repo.repo, "@ant-design/pro-form", &options)
+        .unwrap()
+        .iter()
+        .map(|release| release.version.to_string())
+        .collect()
+    };
+    assert_eq!(
+      versions(PrereleasePolicy::Include),
+      vec!["1.0.0", "1.1.0-beta.1", "1.1.0", "1.2.0-alpha.0"]
+    );
+    assert_eq!(versions(PrereleasePolicy::Exclude), vec!["1.0.0", "1.1.0"]);
+    assert_eq!(
+      versions(PrereleasePolicy::RollUp),
+      vec!["1.0.0", "1.1.0", "1.2.0-alpha.0"]
+    );
+
+    let options = ReleaseOptions {
+      prerelease: PrereleasePolicy::RollUp,
+      latest: LatestRelease::Stable,
+      ..ReleaseOptions::default()
+    };
+    let latest = get_commit_latest_range(&repo.repo, "@ant-design/pro-form", &options).unwrap();
+    assert_eq!(latest.latest_tag.name, "@ant-design/pro-form@1.1.0");
+    // The beta is rolled up, so the range reaches back to 1.0.0.
+    assert_eq!(
+      repo.messages(
+        latest.start.id(),
+        latest.end.map(|end| end.id()),
+        &WalkOptions::default()
+      ),
+      vec!["1.1.0", "1.1.0-beta.1"]
+    );
   }
 
-  Ok(commits)
-}
+  #[test]
+  fn it_parses_tag_patterns() {
+    let pattern = TagPattern::default();
+    assert_eq!(
+      pattern.version("@ant-design/pro-form@1.2.0", "@ant-design/pro-form"),
+      Some(Version::new(1, 2, 0))
+    );
+    assert_eq!(
+      pattern.version("@ant-design/pro-form-extra@1.2.0", "@ant-design/pro-form"),
+      None
+    );
+    assert_eq!(
+      pattern.parse("@ant-design/pro-form@1.2.0-beta.1"),
+      Some((
+        "@ant-design/pro-form".to_string(),
+        Version::parse("1.2.0-beta.1").unwrap()
+      ))
+    );
 
-/// Get all commits for a path.
-pub fn latest_commits(repo: &Repository, package_name: &str) -> crate::Result<(Tag, Vec<Commit>)> {
-  let commit_range = get_commit_latest_range(&repo, package_name)?;
+    let pattern: TagPattern = "{name}-v{version}".parse().unwrap();
+    assert_eq!(pattern.format("form", "1.0.0"), "form-v1.0.0");
+    assert_eq!(pattern.version("form-extra-v1.0.0", "form"), None);
+    assert_eq!(
+      pattern.parse("form-extra-v1.0.0"),
+      Some(("form-extra".to_string(), Version::new(1, 0, 0)))
+    );
 
-  let tag = commit_range.clone().latest_tag;
+    let pattern: TagPattern = "v{version}".parse().unwrap();
+    assert_eq!(
+      pattern.version("v2.0.0", "form"),
+      Some(Version::new(2, 0, 0))
+    );
+    assert_eq!(pattern.version("2.0.0", "form"), None);
 
-  let commits = get_commit_list_by_commit_range(&repo, commit_range).unwrap();
-  Ok((tag, commits))
-}
+    assert!("{name}".parse::<TagPattern>().is_err());
+    assert!("{name}{version}".parse::<TagPattern>().is_err());
+  }
+
+  #[test]
+  fn it_collects_unreleased_commits() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let options = ReleaseOptions::default();
+
+    // Without any release everything is unreleased.
+    assert!(get_unreleased_range(
+      &repo.repo,
+      "@ant-design/pro-form",
+      &root.to_string(),
+      &options,
+    )
+    .unwrap()
+    .end
+    .is_none());
+
+    repo.tag(root, "@ant-design/pro-form@1.0.0");
+    let fix = repo.commit(&[root], "fix: form");
+    let feat = repo.commit(&[fix], "feat: form");
 
-pub fn full_commits(repo: &Repository, package_name: &str) -> crate::Result<Vec<TagAndCommit>> {
-  let commit_range_list = get_all_tag_range(&repo, package_name)?;
-  let mut commit_list: Vec<TagAndCommit> = vec![];
+    let unreleased = get_unreleased_range(
+      &repo.repo,
+      "@ant-design/pro-form",
+      &feat.to_string(),
+      &options,
+    )
+    .unwrap();
+    assert_eq!(unreleased.latest_tag.name, "Unreleased");
+    assert_eq!(
+      repo.messages(
+        feat,
+        unreleased.end.map(|end| end.id()),
+        &WalkOptions::default()
+      ),
+      vec!["feat: form", "fix: form"]
+    );
+  }
 
-  for commit_range in commit_range_list {
-    let tag = commit_range.clone().latest_tag;
-    // 根据 range 找到 commit
-    let commits = get_commit_list_by_commit_range(&repo, commit_range).unwrap();
+  #[test]
+  fn it_walks_ref_ranges() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let base = repo.commit(&[root], "feat: base");
+    let hotfix = repo.commit(&[base], "fix: hotfix");
+    repo
+      .repo
+      .branch("hotfix", &repo.repo.find_commit(hotfix).unwrap(), false)
+      .unwrap();
 
-    commit_list.insert(
-      commit_list.len(),
-      TagAndCommit {
-        tag,
-        commit_list: commits,
+    let range = get_ref_range(
+      &repo.repo,
+      &base.to_string(),
+      "hotfix",
+      &ReleaseOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(range.latest_tag.name, "hotfix");
+    assert_eq!(
+      repo.messages(
+        range.start.id(),
+        range.end.map(|end| end.id()),
+        &WalkOptions::default()
+      ),
+      vec!["fix: hotfix"]
+    );
+    assert!(get_ref_range(&repo.repo, "missing", "hotfix", &ReleaseOptions::default()).is_err());
+  }
+
+  #[test]
+  fn it_walks_ref_ranges_once_for_every_package() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let form = repo.commit_file(root, "packages/form/index.ts", "a\nb\n");
+    let table = repo.commit_file(form, "packages/table/index.ts", "a\n");
+
+    let package_names = vec!["form".to_string(), "table".to_string()];
+    let package_list = package_ref_range_commits(
+      &repo.repo,
+      &root.to_string(),
+      &table.to_string(),
+      &package_names,
+      &ReleaseOptions::default(),
+      |package_name| WalkOptions {
+        collect_paths: true,
+        diff_stats: true,
+        stats_path: Some(format!("packages/{}/", package_name)),
+        ..WalkOptions::default()
       },
+    )
+    .unwrap();
+
+    let hashes: Vec<Vec<&str>> = package_list
+      .iter()
+      .map(|tag_and_commit| {
+        tag_and_commit
+          .commit_list
+          .iter()
+          .map(|commit| commit.hash())
+          .collect()
+      })
+      .collect();
+    assert_eq!(hashes[0], vec![table.to_string(), form.to_string()]);
+    assert_eq!(hashes[0], hashes[1]);
+    assert_eq!(
+      package_list[1].commit_list[0].paths(),
+      &["packages/table/index.ts".to_string()]
     );
+    let insertions: Vec<usize> = package_list
+      .iter()
+      .map(|tag_and_commit| tag_and_commit.stats.as_ref().unwrap().insertions)
+      .collect();
+    assert_eq!(insertions, vec![2, 1]);
+  }
+
+  #[test]
+  fn it_computes_diff_stats() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let form = repo.commit_file(root, "packages/form/index.ts", "a\nb\nc\n");
+    let table = repo.commit_file(form, "packages/table/index.ts", "a\n");
+
+    let stats = diff_stats(&repo.repo, &repo.range(table, Some(root)), None).unwrap();
+    assert_eq!(stats.files_changed, 2);
+    assert_eq!(stats.insertions, 4);
+    assert_eq!(stats.deletions, 0);
+    assert_eq!(stats.top_paths[0].path, "packages/form/index.ts");
+
+    let stats = diff_stats(
+      &repo.repo,
+      &repo.range(table, Some(root)),
+      Some("packages/table/"),
+    )
+    .unwrap();
+    assert_eq!(stats.files_changed, 1);
+    assert_eq!(stats.insertions, 1);
+  }
+
+  #[test]
+  fn it_collects_commit_paths() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let table = repo.commit_file(root, "packages/table/index.ts", "a\n");
+    let table = repo.commit_file(table, "packages/table/src/Table.tsx", "a\n");
+
+    let paths = |id: Oid| commit_paths(&repo.repo, &repo.repo.find_commit(id).unwrap()).unwrap();
+    assert_eq!(paths(root), vec!["1600000060.txt"]);
+    assert_eq!(paths(table), vec!["packages/table/src/Table.tsx"]);
   }
 
-  Ok(commit_list)
+  #[test]
+  fn it_uses_annotated_tag_dates() {
+    let mut repo = TestRepo::new();
+    let root = repo.commit(&[], "root");
+    let commit = repo.repo.find_commit(root).unwrap();
+    // Tagged at 2020-09-14 16:00 UTC, which is already the next day in UTC+8.
+    let tagger = Signature::new(
+      "doctor",
+      "doctor@example.com",
+      &Time::new(1_600_099_200, 8 * 60),
+    )
+    .unwrap();
+    repo
+      .repo
+      .tag(
+        "@ant-design/pro-form@1.0.0",
+        commit.as_object(),
+        &tagger,
+        "First release\n",
+        false,
+      )
+      .unwrap();
+    drop(commit);
+
+    let history = tag_history(
+      &repo.repo,
+      "@ant-design/pro-form",
+      &ReleaseOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(history[0].tag().date_time, "2020-09-14");
+    assert_eq!(history[0].tag().summary.as_deref(), Some("First release"));
+
+    let options = ReleaseOptions {
+      date: DateOptions::new("%Y-%m-%d %H:%M", "original".parse().unwrap()).unwrap(),
+      ..ReleaseOptions::default()
+    };
+    let history = tag_history(&repo.repo, "@ant-design/pro-form", &options).unwrap();
+    assert_eq!(history[0].tag().date_time, "2020-09-15 00:00");
+
+    assert!(DateOptions::new("%Q", Timezone::Utc).is_err());
+    assert!("+25:00".parse::<Timezone>().is_err());
+  }
 }
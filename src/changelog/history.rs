@@ -0,0 +1,312 @@
+use failure::ResultExt;
+use git2::{Oid, Repository, Sort};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::git::{
+  get_commit_list_by_commit_range, package_tag_history, range_stats, to_commit, Commit,
+  CommitRange, ReleaseOptions, TagAndCommit, WalkOptions,
+};
+use crate::ErrorKind;
+
+/// A fixed size set of commit indexes.
+#[derive(Clone, Debug)]
+struct BitSet {
+  words: Vec<u64>,
+}
+
+impl BitSet {
+  fn new(len: usize) -> BitSet {
+    BitSet {
+      words: vec![0; len.div_ceil(64)],
+    }
+  }
+
+  /// Insert an index, returns whether it was missing.
+  fn insert(&mut self, index: usize) -> bool {
+    let (word, bit) = (index / 64, 1 << (index % 64));
+    let missing = self.words[word] & bit == 0;
+    self.words[word] |= bit;
+    missing
+  }
+
+  fn contains(&self, index: usize) -> bool {
+    self.words[index / 64] & (1 << (index % 64)) != 0
+  }
+}
+
+/// A commit of the graph, parents are indexes into [`History::nodes`].
+#[derive(Debug)]
+struct Node {
+  id: Oid,
+  parents: Vec<usize>,
+  merge: bool,
+}
+
+/// The commit graph reachable from a set of tips, walked once.
+///
+/// Nodes are kept in topological order, newest first, so every ancestor of
+/// a node comes after it. The ancestors of each range boundary are computed
+/// in memory and cached, so ranges of every package that share boundaries
+/// or history don't go back to git.
+pub struct History<'r> {
+  repo: &'r Repository,
+  options: WalkOptions,
+  nodes: Vec<Node>,
+  index: HashMap<Oid, usize>,
+  ancestors: HashMap<usize, Rc<BitSet>>,
+  commits: Vec<Option<Commit>>,
+}
+
+impl<'r> History<'r> {
+  /// Walk the history reachable from `tips`. Only the commit graph is read,
+  /// commit details are loaded when a range first needs them.
+  pub fn new(
+    repo: &'r Repository,
+    tips: impl IntoIterator<Item = Oid>,
+    options: &WalkOptions,
+  ) -> crate::Result<History<'r>> {
+    let mut revwalk = repo.revwalk().context(ErrorKind::Git)?;
+    revwalk
+      .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+      .context(ErrorKind::Git)?;
+    if options.first_parent {
+      revwalk.simplify_first_parent().context(ErrorKind::Git)?;
+    }
+    for tip in tips {
+      revwalk.push(tip).context(ErrorKind::Git)?;
+    }
+    let ids = revwalk
+      .collect::<Result<Vec<Oid>, _>>()
+      .context(ErrorKind::Git)?;
+    let index: HashMap<Oid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut nodes = Vec::with_capacity(ids.len());
+    for id in ids {
+      let commit = repo.find_commit(id).context(ErrorKind::Git)?;
+      // The first parent walk only follows the first parent, so the others
+      // are not part of the graph.
+      let parent_count = if options.first_parent { 1 } else { usize::MAX };
+      let parents = commit
+        .parent_ids()
+        .take(parent_count)
+        .filter_map(|parent| index.get(&parent).copied())
+        .collect();
+      nodes.push(Node {
+        id,
+        parents,
+        merge: commit.parent_count() > 1,
+      });
+    }
+
+    let commits = nodes.iter().map(|_| None).collect();
+    Ok(History {
+      repo,
+      options: options.clone(),
+      nodes,
+      index,
+      ancestors: HashMap::new(),
+      commits,
+    })
+  }
+
+  /// The node and all of its ancestors.
+  fn ancestors(&mut self, node: usize) -> Rc<BitSet> {
+    if let Some(ancestors) = self.ancestors.get(&node) {
+      return ancestors.clone();
+    }
+    let mut ancestors = BitSet::new(self.nodes.len());
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+      if ancestors.insert(current) {
+        stack.extend(&self.nodes[current].parents);
+      }
+    }
+    let ancestors = Rc::new(ancestors);
+    self.ancestors.insert(node, ancestors.clone());
+    ancestors
+  }
+
+  fn commit(&mut self, node: usize) -> crate::Result<Commit> {
+    if let Some(commit) = &self.commits[node] {
+      return Ok(commit.clone());
+    }
+    let git_commit = self
+      .repo
+      .find_commit(self.nodes[node].id)
+      .context(ErrorKind::Git)?;
+    let commit = to_commit(self.repo, &git_commit, &self.options)?;
+    self.commits[node] = Some(commit.clone());
+    Ok(commit)
+  }
+
+  /// Get the commits of a range, the same as
+  /// [`get_commit_list_by_commit_range`]. Ranges with ends outside of the
+  /// walked history fall back to a walk of their own.
+  pub fn commits_in(&mut self, commit_range: &CommitRange) -> crate::Result<Vec<Commit>> {
+    let start = self.index.get(&commit_range.start().id()).copied();
+    let end = commit_range
+      .end()
+      .map(|end| self.index.get(&end.id()).copied());
+    let (start, end) = match (start, end) {
+      (Some(start), None) => (start, None),
+      (Some(start), Some(Some(end))) => (start, Some(end)),
+      _ => return get_commit_list_by_commit_range(self.repo, commit_range.clone(), &self.options),
+    };
+
+    let included = self.ancestors(start);
+    let hidden = end.map(|end| self.ancestors(end));
+    let mut commits = vec![];
+    for node in start..self.nodes.len() {
+      if !included.contains(node)
+        || hidden.as_ref().is_some_and(|hidden| hidden.contains(node))
+        || (self.options.no_merges && self.nodes[node].merge)
+      {
+        continue;
+      }
+      commits.push(self.commit(node)?);
+    }
+    Ok(commits)
+  }
+
+  /// Collect the commits of a range, and its diff statistics when
+  /// `options.diff_stats` is enabled.
+  pub fn tag_and_commit(
+    &mut self,
+    commit_range: CommitRange,
+    options: &WalkOptions,
+  ) -> crate::Result<TagAndCommit> {
+    let stats = range_stats(self.repo, &commit_range, options)?;
+    let commit_list = self.commits_in(&commit_range)?;
+    Ok(TagAndCommit {
+      tag: commit_range.tag().clone(),
+      commit_list,
+      stats,
+    })
+  }
+}
+
+/// Get the commits of every release of every package, each from the newest
+/// to the oldest release. Tags are listed and the history is walked once
+/// for all packages. `package_options` gives the walk options of each
+/// package, only the diff statistics may differ between them.
+pub fn package_full_commits(
+  repo: &Repository,
+  package_names: &[String],
+  release_options: &ReleaseOptions,
+  package_options: impl Fn(&str) -> WalkOptions,
+) -> crate::Result<Vec<Vec<TagAndCommit>>> {
+  let release_lists = package_tag_history(repo, package_names, release_options)?;
+  let tips = release_lists
+    .iter()
+    .flatten()
+    .map(|release| release.range.start().id());
+  let options = package_names
+    .first()
+    .map(|package_name| package_options(package_name))
+    .unwrap_or_default();
+  let mut history = History::new(repo, tips, &options)?;
+
+  let mut package_list = vec![];
+  for (package_name, release_list) in package_names.iter().zip(release_lists) {
+    let options = package_options(package_name);
+    let mut commit_list = vec![];
+    for release in release_list.into_iter().rev() {
+      commit_list.push(history.tag_and_commit(release.range, &options)?);
+    }
+    package_list.push(commit_list);
+  }
+  Ok(package_list)
+}
+
+#[cfg(test)]
+mod tests {
+  use git2::{Oid, Repository, Signature, Time};
+  use tempfile::TempDir;
+
+  use super::History;
+  use crate::changelog::git::{
+    get_commit_list_by_commit_range, tag_history, ReleaseOptions, WalkOptions,
+  };
+
+  fn commit(repo: &Repository, parents: &[Oid], time: i64) -> Oid {
+    let signature = Signature::new("doctor", "doctor@example.com", &Time::new(time, 0)).unwrap();
+    let parents: Vec<_> = parents
+      .iter()
+      .map(|id| repo.find_commit(*id).unwrap())
+      .collect();
+    let mut builder = repo.treebuilder(None).unwrap();
+    let blob = repo.blob(time.to_string().as_bytes()).unwrap();
+    builder.insert("file.txt", blob, 0o100644).unwrap();
+    let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+    let parent_refs: Vec<_> = parents.iter().collect();
+    repo
+      .commit(
+        None,
+        &signature,
+        &signature,
+        &time.to_string(),
+        &tree,
+        &parent_refs,
+      )
+      .unwrap()
+  }
+
+  #[test]
+  fn it_matches_the_revwalk() {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let mut time = 1_600_000_000;
+    let mut main = commit(&repo, &[], time);
+    for release in 0..6 {
+      time += 60;
+      let branch = commit(&repo, &[main], time);
+      time += 60;
+      main = commit(&repo, &[main], time);
+      time += 60;
+      main = commit(&repo, &[main, branch], time);
+      let tagged = repo.find_commit(main).unwrap();
+      repo
+        .tag_lightweight(
+          &format!("@ant-design/pro-form@1.{}.0", release),
+          tagged.as_object(),
+          false,
+        )
+        .unwrap();
+    }
+
+    for options in [
+      WalkOptions::default(),
+      WalkOptions {
+        first_parent: true,
+        ..WalkOptions::default()
+      },
+      WalkOptions {
+        no_merges: true,
+        ..WalkOptions::default()
+      },
+    ] {
+      let release_list =
+        tag_history(&repo, "@ant-design/pro-form", &ReleaseOptions::default()).unwrap();
+      let mut history = History::new(
+        &repo,
+        release_list
+          .iter()
+          .map(|release| release.range.start().id()),
+        &options,
+      )
+      .unwrap();
+      for release in release_list {
+        let hashes = |commits: Vec<crate::changelog::git::Commit>| -> Vec<String> {
+          let mut hashes: Vec<String> = commits.iter().map(|c| c.hash().to_string()).collect();
+          hashes.sort();
+          hashes
+        };
+        assert_eq!(
+          hashes(history.commits_in(&release.range).unwrap()),
+          hashes(get_commit_list_by_commit_range(&repo, release.range, &options).unwrap())
+        );
+      }
+    }
+  }
+}
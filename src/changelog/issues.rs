@@ -0,0 +1,192 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// What an issue link points to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueKind {
+  /// A GitHub issue, or a number the forge couldn't tell apart.
+  Issue,
+  /// A GitHub pull request referenced by number.
+  PullRequest,
+  /// A key of the configured issue tracker.
+  Jira,
+}
+
+/// An issue referenced by a commit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IssueLink {
+  pub kind: IssueKind,
+  /// The reference as written, `#12`, `org/repo#34` or `PROJ-56`.
+  pub label: String,
+  pub url: String,
+}
+
+/// An issue reference found in a commit message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IssueRef {
+  /// `#12`, or `org/repo#34` for another repository.
+  GitHub {
+    repo: Option<String>,
+    number: String,
+  },
+  Jira(String),
+}
+
+impl IssueRef {
+  /// The reference as written in the message.
+  #[must_use]
+  pub fn label(&self) -> String {
+    match self {
+      IssueRef::GitHub {
+        repo: Some(repo),
+        number,
+      } => format!("{}#{}", repo, number),
+      IssueRef::GitHub { repo: None, number } => format!("#{}", number),
+      IssueRef::Jira(key) => key.clone(),
+    }
+  }
+}
+
+/// Jira style keys such as `PROJ-56`, linked with a URL template.
+#[derive(Clone, Debug)]
+pub struct JiraKeys {
+  pattern: Regex,
+  url: String,
+}
+
+impl JiraKeys {
+  pub const DEFAULT_PATTERN: &'static str = "[A-Z][A-Z0-9]+-[0-9]+";
+
+  /// Match keys with `pattern`, or [`JiraKeys::DEFAULT_PATTERN`], and link
+  /// them with `url` where `{key}` is replaced by the key.
+  pub fn new(pattern: Option<&str>, url: &str) -> crate::Result<JiraKeys> {
+    let pattern = pattern.unwrap_or(JiraKeys::DEFAULT_PATTERN);
+    let regex = Regex::new(&format!(r"\b(?:{})\b", pattern))
+      .map_err(|_| ErrorKind::Config(t!("changelog.jira_pattern_invalid", pattern)))?;
+    if !url.contains("{key}") {
+      return Err(ErrorKind::Config(t!("changelog.jira_url_invalid", url)).into());
+    }
+    Ok(JiraKeys {
+      pattern: regex,
+      url: url.to_string(),
+    })
+  }
+
+  /// The page of a key.
+  #[must_use]
+  pub fn url(&self, key: &str) -> String {
+    self.url.replace("{key}", key)
+  }
+}
+
+/// A closing keyword followed by one or more comma separated references.
+static CLOSING: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+((?:[\w.-]+/[\w.-]+)?#[0-9]+(?:\s*,\s*(?:[\w.-]+/[\w.-]+)?#[0-9]+)*)",
+  )
+  .unwrap()
+});
+
+/// A GitHub reference, `#12` or `org/repo#34`.
+static REFERENCE: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"(?:([\w.-]+/[\w.-]+))?#([0-9]+)").unwrap());
+
+/// Find the issues a commit message closes, such as `closes #12` or
+/// `Fixes: org/repo#34` in the subject, body or trailers, then the Jira keys
+/// anywhere in the message. Every reference is listed once.
+#[must_use]
+pub fn parse_issue_refs(message: &str, jira: Option<&JiraKeys>) -> Vec<IssueRef> {
+  let mut refs: Vec<IssueRef> = vec![];
+  for captures in CLOSING.captures_iter(message) {
+    let github_refs = REFERENCE
+      .captures_iter(&captures[1])
+      .map(|reference| IssueRef::GitHub {
+        repo: reference.get(1).map(|repo| repo.as_str().to_string()),
+        number: reference[2].to_string(),
+      });
+    merge_issue_refs(&mut refs, github_refs);
+  }
+  if let Some(jira) = jira {
+    let jira_refs = jira
+      .pattern
+      .find_iter(message)
+      .map(|key| IssueRef::Jira(key.as_str().to_string()));
+    merge_issue_refs(&mut refs, jira_refs);
+  }
+  refs
+}
+
+/// Append the references missing from `refs`, such as the issues a pull
+/// request description closes after the ones of its commit.
+pub fn merge_issue_refs(refs: &mut Vec<IssueRef>, more: impl IntoIterator<Item = IssueRef>) {
+  for issue_ref in more {
+    if !refs.contains(&issue_ref) {
+      refs.push(issue_ref);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{merge_issue_refs, parse_issue_refs, IssueRef, JiraKeys};
+
+  fn github(repo: Option<&str>, number: &str) -> IssueRef {
+    IssueRef::GitHub {
+      repo: repo.map(String::from),
+      number: number.to_string(),
+    }
+  }
+
+  #[test]
+  fn it_parses_issue_refs() {
+    let jira = JiraKeys::new(None, "https://jira.example.com/browse/{key}").unwrap();
+    let message = "fix(form): closes #12 and PROJ-7 (#42)\n\nFixes org/repo#34, #12\nresolved #5\n\nCloses: #56\nRefs: PROJ-7, OPS-1";
+    assert_eq!(
+      parse_issue_refs(message, Some(&jira)),
+      vec![
+        github(None, "12"),
+        github(Some("org/repo"), "34"),
+        github(None, "5"),
+        github(None, "56"),
+        IssueRef::Jira("PROJ-7".to_string()),
+        IssueRef::Jira("OPS-1".to_string()),
+      ]
+    );
+    assert_eq!(parse_issue_refs("feat: prefix #12 (#42)", None), vec![]);
+    assert_eq!(jira.url("OPS-1"), "https://jira.example.com/browse/OPS-1");
+    assert_eq!(github(Some("org/repo"), "34").label(), "org/repo#34");
+  }
+
+  #[test]
+  fn it_merges_pull_request_refs() {
+    let mut refs = parse_issue_refs("fix(form): closes #12 (#42)", None);
+    merge_issue_refs(
+      &mut refs,
+      parse_issue_refs("## Summary\n\nFixes #12, #13\nCloses org/repo#12", None),
+    );
+    assert_eq!(
+      refs,
+      vec![
+        github(None, "12"),
+        github(None, "13"),
+        github(Some("org/repo"), "12"),
+      ]
+    );
+  }
+
+  #[test]
+  fn it_validates_jira_keys() {
+    let jira = JiraKeys::new(Some("DOC-[0-9]+"), "https://jira.example.com/{key}").unwrap();
+    assert_eq!(
+      parse_issue_refs("feat: DOC-1 PROJ-2", Some(&jira)),
+      vec![IssueRef::Jira("DOC-1".to_string())]
+    );
+    assert!(JiraKeys::new(Some("DOC-[0-9"), "https://jira.example.com/{key}").is_err());
+    assert!(JiraKeys::new(None, "https://jira.example.com/").is_err());
+  }
+}
@@ -0,0 +1,1131 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::git::DiffStats;
+use super::issues::{IssueKind, IssueLink};
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// The pull request a commit was merged with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequest {
+  /// The pull request number, without the `#`.
+  pub number: String,
+  pub url: String,
+  /// The title, when pull request details are enabled.
+  pub title: Option<String>,
+  /// The label names, when pull request details are enabled.
+  pub labels: Vec<String>,
+  /// The GitHub login of who merged the pull request, when pull request
+  /// details are enabled.
+  pub merged_by: Option<String>,
+}
+
+/// A commit of a release.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+  /// The first line of the commit message.
+  pub message: String,
+  pub hash: String,
+  pub short_hash: String,
+  /// The commit page on GitHub.
+  pub url: String,
+  pub pull_request: Option<PullRequest>,
+  /// The GitHub login of the author when known, the git author name
+  /// otherwise.
+  pub author: String,
+  /// The hash of the reverted commit, for reverts of a commit outside the
+  /// release.
+  pub reverts: Option<String>,
+  /// The issues the commit closes and the Jira keys it mentions.
+  pub issues: Vec<IssueLink>,
+}
+
+impl Entry {
+  /// The text shown for the entry, the pull request title when known and the
+  /// commit subject otherwise.
+  #[must_use]
+  pub fn title(&self) -> &str {
+    self
+      .pull_request
+      .as_ref()
+      .and_then(|pull_request| pull_request.title.as_deref())
+      .unwrap_or(&self.message)
+  }
+
+  /// Whether the entry reverts a commit of an earlier release.
+  #[must_use]
+  pub fn is_revert(&self) -> bool {
+    self.reverts.is_some()
+  }
+
+  /// The conventional commit type, `feat` for `feat(form): ...`.
+  #[must_use]
+  pub fn kind(&self) -> Option<&str> {
+    conventional_kind(&self.message)
+  }
+}
+
+/// Someone who committed to a release.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contributor {
+  /// The GitHub login when a pull request lookup found it, the git author
+  /// name otherwise.
+  pub name: String,
+  /// The GitHub profile, only for logins.
+  pub url: Option<String>,
+  pub avatar_url: Option<String>,
+  /// The number of entries by the contributor.
+  pub commits: usize,
+  /// Whether the release lists the first commit of the contributor in the
+  /// history of every tag. A first commit that was excluded, or attributed
+  /// to another package, is not marked.
+  pub first_time: bool,
+}
+
+impl Contributor {
+  /// A contributor with one commit, known by GitHub login.
+  #[must_use]
+  pub fn github(login: &str) -> Contributor {
+    Contributor {
+      url: Some(format!("https://github.com/{}", login)),
+      avatar_url: Some(format!("https://github.com/{}.png?size=40", login)),
+      ..Contributor::git(login)
+    }
+  }
+
+  /// A contributor with one commit, known by git author name only.
+  #[must_use]
+  pub fn git(name: &str) -> Contributor {
+    Contributor {
+      name: name.to_string(),
+      url: None,
+      avatar_url: None,
+      commits: 1,
+      first_time: false,
+    }
+  }
+
+  /// The number of commits, and a note for first-time contributors.
+  #[must_use]
+  pub fn summary(&self) -> String {
+    let commits = if self.commits == 1 {
+      t!("changelog.contributor_commit")
+    } else {
+      t!("changelog.contributor_commits", self.commits)
+    };
+    if self.first_time {
+      format!("{} 🎉 {}", commits, t!("changelog.first_contribution"))
+    } else {
+      commits
+    }
+  }
+}
+
+/// Merge the contributors with the same name, most commits first and in
+/// order of appearance otherwise.
+#[must_use]
+pub fn merge_contributors(contributors: impl IntoIterator<Item = Contributor>) -> Vec<Contributor> {
+  let mut merged: Vec<Contributor> = vec![];
+  for contributor in contributors {
+    match merged
+      .iter_mut()
+      .find(|known| known.name == contributor.name)
+    {
+      Some(known) => {
+        known.commits += contributor.commits;
+        known.first_time |= contributor.first_time;
+      }
+      None => merged.push(contributor),
+    }
+  }
+  merged.sort_by_key(|contributor| Reverse(contributor.commits));
+  merged
+}
+
+/// The conventional commit type of a subject, `feat` for `feat(form): ...`.
+#[must_use]
+pub fn conventional_kind(subject: &str) -> Option<&str> {
+  let (head, _) = subject.split_once(':')?;
+  let kind = head.split('(').next()?.trim_end_matches('!');
+  if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+    Some(kind)
+  } else {
+    None
+  }
+}
+
+/// A release of a package.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+  /// The heading of the release, the tag name or the range `from...to`.
+  pub title: String,
+  /// The page comparing the ends of a range, linked from the heading.
+  pub compare_url: Option<String>,
+  pub tag: String,
+  pub date: String,
+  /// The annotated tag message, when enabled.
+  pub summary: Option<String>,
+  pub entries: Vec<Entry>,
+  /// The authors of the entries, most commits first.
+  pub contributors: Vec<Contributor>,
+  /// The diff statistics, when enabled and something changed.
+  pub stats: Option<DiffStats>,
+}
+
+/// The releases of a package, newest first.
+#[derive(Clone, Debug, Serialize)]
+pub struct PackageNotes {
+  pub package: String,
+  pub releases: Vec<ReleaseNotes>,
+}
+
+/// Render the releases of every package to a changelog document.
+pub trait Renderer {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String>;
+}
+
+/// The built in output formats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+  /// One bullet per commit, the release heading is written with the first
+  /// package.
+  #[default]
+  Markdown,
+  /// <https://keepachangelog.com>, commits grouped by change type.
+  KeepAChangelog,
+  Json,
+  Yaml,
+  /// A self-contained HTML page.
+  Html,
+}
+
+impl OutputFormat {
+  #[must_use]
+  pub fn renderer(self) -> Box<dyn Renderer> {
+    match self {
+      OutputFormat::Markdown => Box::new(Markdown),
+      OutputFormat::KeepAChangelog => Box::new(KeepAChangelog::default()),
+      OutputFormat::Json => Box::new(Json),
+      OutputFormat::Yaml => Box::new(Yaml),
+      OutputFormat::Html => Box::new(Html::default()),
+    }
+  }
+
+  /// The file extension of the rendered document.
+  #[must_use]
+  pub fn extension(self) -> &'static str {
+    match self {
+      OutputFormat::Markdown | OutputFormat::KeepAChangelog => "md",
+      OutputFormat::Json => "json",
+      OutputFormat::Yaml => "yaml",
+      OutputFormat::Html => "html",
+    }
+  }
+}
+
+impl FromStr for OutputFormat {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<OutputFormat> {
+    match s {
+      "markdown" => Ok(OutputFormat::Markdown),
+      "keep-a-changelog" => Ok(OutputFormat::KeepAChangelog),
+      "json" => Ok(OutputFormat::Json),
+      "yaml" => Ok(OutputFormat::Yaml),
+      "html" => Ok(OutputFormat::Html),
+      _ => Err(ErrorKind::Config(t!("changelog.format_invalid", s)).into()),
+    }
+  }
+}
+
+/// A release and the changes of every package in it.
+struct ReleaseGroup<'a> {
+  release: &'a ReleaseNotes,
+  packages: Vec<(&'a str, &'a ReleaseNotes)>,
+}
+
+/// Group the releases of all packages by title, in order of appearance.
+/// Ranges render every package under the same title, while the full
+/// history has a title per package tag.
+fn group_releases(packages: &[PackageNotes]) -> Vec<ReleaseGroup<'_>> {
+  let mut groups: Vec<ReleaseGroup> = vec![];
+  for package in packages {
+    for release in &package.releases {
+      match groups
+        .iter_mut()
+        .find(|group| group.release.title == release.title)
+      {
+        Some(group) => group.packages.push((&package.package, release)),
+        None => groups.push(ReleaseGroup {
+          release,
+          packages: vec![(&package.package, release)],
+        }),
+      }
+    }
+  }
+  groups
+}
+
+/// The entries of a release, then the reverts listed in their own section.
+fn split_reverts(entries: &[Entry]) -> (Vec<&Entry>, Vec<&Entry>) {
+  entries.iter().partition(|entry| !entry.is_revert())
+}
+
+/// The markdown heading of a release, linked to the comparison of a range.
+fn release_title(release: &ReleaseNotes) -> String {
+  match &release.compare_url {
+    Some(url) => format!("[{title}]({url})", title = release.title, url = url),
+    None => release.title.clone(),
+  }
+}
+
+/// The markdown link of a contributor, the plain name without a GitHub
+/// login.
+fn contributor_link(contributor: &Contributor) -> String {
+  match &contributor.url {
+    Some(url) => format!("[@{name}]({url})", name = contributor.name, url = url),
+    None => contributor.name.clone(),
+  }
+}
+
+/// The login of who merged the pull request of an entry, when known.
+fn merged_by(entry: &Entry) -> Option<&str> {
+  entry
+    .pull_request
+    .as_ref()
+    .and_then(|pull_request| pull_request.merged_by.as_deref())
+}
+
+/// The markdown link of an entry, the pull request when there is one, then
+/// the issues.
+fn entry_links(entry: &Entry) -> String {
+  let mut links = match &entry.pull_request {
+    Some(pull_request) => format!(
+      "[#{number}]({url}) [@{author}](https://github.com/{author})",
+      number = pull_request.number,
+      url = pull_request.url,
+      author = entry.author
+    ),
+    None => format!(
+      "[{short_hash}]({url})",
+      short_hash = entry.short_hash,
+      url = entry.url
+    ),
+  };
+  if let Some(merged_by) = merged_by(entry) {
+    links.push_str(&format!(
+      " {label} [@{login}](https://github.com/{login})",
+      label = t!("changelog.merged_by"),
+      login = merged_by
+    ));
+  }
+  for issue in &entry.issues {
+    links.push_str(&format!(" [{}]({})", issue.label, issue.url));
+  }
+  links
+}
+
+pub struct Markdown;
+
+impl Markdown {
+  fn entry(entry: &Entry) -> String {
+    format!(
+      "* {message}. {links}\n",
+      message = entry.title(),
+      links = entry_links(entry)
+    )
+  }
+
+  fn stats(package: &str, stats: &DiffStats) -> String {
+    let mut md_content = format!(
+      "\n<details><summary>{package}: {summary}</summary>\n\n",
+      package = package,
+      summary = stats.summary()
+    );
+    for path_stats in &stats.top_paths {
+      md_content.push_str(&format!(
+        "- `{path}` +{insertions} -{deletions}\n",
+        path = path_stats.path,
+        insertions = path_stats.insertions,
+        deletions = path_stats.deletions
+      ));
+    }
+    md_content.push_str("\n</details>\n\n");
+    md_content
+  }
+}
+
+impl Renderer for Markdown {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    let mut md_content = String::new();
+    for (index, package) in packages.iter().enumerate() {
+      for (release_index, release) in package.releases.iter().enumerate() {
+        if index == 0 {
+          if release_index > 0 {
+            md_content.push('\n');
+          }
+          md_content.push_str(&format!(
+            "## {title}\n\n`{date}`\n\n",
+            title = release_title(release),
+            date = release.date
+          ));
+          if let Some(summary) = &release.summary {
+            md_content.push_str(&format!("{}\n\n", summary));
+          }
+        }
+        let (entries, reverts) = split_reverts(&release.entries);
+        for entry in entries {
+          md_content.push_str(&Markdown::entry(entry));
+        }
+        if !reverts.is_empty() {
+          md_content.push_str(&format!("\n### {}\n\n", CommitTypes::heading("Reverts")));
+          for entry in reverts {
+            md_content.push_str(&Markdown::entry(entry));
+          }
+        }
+        if !release.contributors.is_empty() {
+          md_content.push_str(&format!(
+            "\n### {}\n\n",
+            CommitTypes::heading("Contributors")
+          ));
+          for contributor in &release.contributors {
+            md_content.push_str(&format!(
+              "* {link}: {summary}\n",
+              link = contributor_link(contributor),
+              summary = contributor.summary()
+            ));
+          }
+        }
+        if let Some(stats) = &release.stats {
+          md_content.push_str(&Markdown::stats(&package.package, stats));
+        }
+      }
+    }
+    Ok(md_content)
+  }
+}
+
+/// The Keep a Changelog section of each conventional commit type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitTypes {
+  sections: HashMap<String, &'static str>,
+  /// The section of pull request labels, checked before the commit type.
+  labels: HashMap<String, &'static str>,
+}
+
+impl CommitTypes {
+  /// The sections in the order of the specification.
+  pub const SECTIONS: [&'static str; 6] = [
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+  ];
+
+  /// Map more commit types, or move the default ones, to a section.
+  pub fn with_sections<'a>(
+    mut self,
+    sections: impl IntoIterator<Item = (&'a str, &'a str)>,
+  ) -> crate::Result<CommitTypes> {
+    for (kind, section) in sections {
+      let section = CommitTypes::known_section(kind, section)?;
+      self.sections.insert(kind.to_string(), section);
+    }
+    Ok(self)
+  }
+
+  /// Map more pull request labels, or move the default ones, to a section.
+  /// Labels are compared case-insensitively.
+  pub fn with_labels<'a>(
+    mut self,
+    labels: impl IntoIterator<Item = (&'a str, &'a str)>,
+  ) -> crate::Result<CommitTypes> {
+    for (label, section) in labels {
+      let section = CommitTypes::known_section(label, section)?;
+      self.labels.insert(label.to_lowercase(), section);
+    }
+    Ok(self)
+  }
+
+  fn known_section(name: &str, section: &str) -> crate::Result<&'static str> {
+    CommitTypes::SECTIONS
+      .into_iter()
+      .find(|known| *known == section)
+      .ok_or_else(|| {
+        ErrorKind::Config(t!(
+          "changelog.section_invalid",
+          name,
+          section,
+          CommitTypes::SECTIONS.join(", ")
+        ))
+        .into()
+      })
+  }
+
+  /// The heading of a section in the current locale.
+  #[must_use]
+  pub fn heading(section: &str) -> String {
+    t!(&format!("section.{}", section))
+  }
+
+  /// The section of an entry, from the first mapped pull request label, then
+  /// the commit type, `Changed` for unmapped types.
+  #[must_use]
+  pub fn section(&self, entry: &Entry) -> &'static str {
+    entry
+      .pull_request
+      .iter()
+      .flat_map(|pull_request| &pull_request.labels)
+      .find_map(|label| self.labels.get(&label.to_lowercase()).copied())
+      .or_else(|| {
+        entry
+          .kind()
+          .and_then(|kind| self.sections.get(kind).copied())
+      })
+      .unwrap_or("Changed")
+  }
+}
+
+impl Default for CommitTypes {
+  fn default() -> CommitTypes {
+    let sections = [("feat", "Added"), ("fix", "Fixed"), ("revert", "Removed")]
+      .into_iter()
+      .map(|(kind, section)| (kind.to_string(), section))
+      .collect();
+    let labels = [
+      ("enhancement", "Added"),
+      ("feature", "Added"),
+      ("bug", "Fixed"),
+      ("breaking", "Changed"),
+      ("deprecation", "Deprecated"),
+      ("removal", "Removed"),
+      ("security", "Security"),
+    ]
+    .into_iter()
+    .map(|(label, section)| (label.to_string(), section))
+    .collect();
+    CommitTypes { sections, labels }
+  }
+}
+
+#[derive(Default)]
+pub struct KeepAChangelog {
+  commit_types: CommitTypes,
+}
+
+impl KeepAChangelog {
+  #[must_use]
+  pub fn new(commit_types: CommitTypes) -> KeepAChangelog {
+    KeepAChangelog { commit_types }
+  }
+
+  fn line(package: &str, entry: &Entry) -> String {
+    format!(
+      "- **{package}**: {message} ({links})\n",
+      package = package,
+      message = entry.title(),
+      links = entry_links(entry)
+    )
+  }
+}
+
+impl Renderer for KeepAChangelog {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    let mut md_content = format!(
+      "# {title}\n\n{intro}\n\n{based_on}\n",
+      title = t!("changelog.title"),
+      intro = t!("changelog.intro"),
+      based_on = t!("changelog.based_on")
+    );
+    let groups = group_releases(packages);
+    for group in &groups {
+      md_content.push_str(&format!(
+        "\n## [{title}] - {date}\n",
+        title = group.release.title,
+        date = group.release.date
+      ));
+      if let Some(summary) = &group.release.summary {
+        md_content.push_str(&format!("\n{}\n", summary));
+      }
+      for section in CommitTypes::SECTIONS {
+        let lines: Vec<String> = group
+          .packages
+          .iter()
+          .flat_map(|(package, release)| release.entries.iter().map(move |entry| (package, entry)))
+          .filter(|(_, entry)| !entry.is_revert() && self.commit_types.section(entry) == section)
+          .map(|(package, entry)| KeepAChangelog::line(package, entry))
+          .collect();
+        if !lines.is_empty() {
+          md_content.push_str(&format!("\n### {}\n\n", CommitTypes::heading(section)));
+          md_content.push_str(&lines.concat());
+        }
+      }
+      let reverts: Vec<String> = group
+        .packages
+        .iter()
+        .flat_map(|(package, release)| release.entries.iter().map(move |entry| (package, entry)))
+        .filter(|(_, entry)| entry.is_revert())
+        .map(|(package, entry)| KeepAChangelog::line(package, entry))
+        .collect();
+      if !reverts.is_empty() {
+        md_content.push_str(&format!("\n### {}\n\n", CommitTypes::heading("Reverts")));
+        md_content.push_str(&reverts.concat());
+      }
+      let contributors = merge_contributors(
+        group
+          .packages
+          .iter()
+          .flat_map(|(_, release)| release.contributors.iter().cloned()),
+      );
+      if !contributors.is_empty() {
+        md_content.push_str(&format!(
+          "\n### {}\n\n",
+          CommitTypes::heading("Contributors")
+        ));
+        for contributor in &contributors {
+          md_content.push_str(&format!(
+            "- {link}: {summary}\n",
+            link = contributor_link(contributor),
+            summary = contributor.summary()
+          ));
+        }
+      }
+    }
+    // the headings of ranges link to the comparison with reference links
+    let links: Vec<String> = groups
+      .iter()
+      .filter_map(|group| {
+        let url = group.release.compare_url.as_ref()?;
+        Some(format!("[{}]: {}\n", group.release.title, url))
+      })
+      .collect();
+    if !links.is_empty() {
+      md_content.push('\n');
+      md_content.push_str(&links.concat());
+    }
+    Ok(md_content)
+  }
+}
+
+pub struct Json;
+
+impl Renderer for Json {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    serde_json::to_string_pretty(packages)
+      .map_err(|err| ErrorKind::Config(t!("changelog.render_failed", "json", err)).into())
+  }
+}
+
+pub struct Yaml;
+
+impl Renderer for Yaml {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    serde_yaml::to_string(packages)
+      .map_err(|err| ErrorKind::Config(t!("changelog.render_failed", "yaml", err)).into())
+  }
+}
+
+#[derive(Default)]
+pub struct Html {
+  /// Show the GitHub avatar of the contributors.
+  avatars: bool,
+}
+
+impl Html {
+  const STYLE: &'static str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Helvetica,Arial,sans-serif;max-width:860px;margin:0 auto;padding:24px;color:#24292f;line-height:1.6}h2{border-bottom:1px solid #d0d7de;padding-bottom:4px}time{color:#57606a}h3{font-size:16px;margin-bottom:4px}a{color:#0969da;text-decoration:none}code{font-size:85%}img{vertical-align:middle;border-radius:50%}";
+
+  /// Show the GitHub avatar of the contributors.
+  #[must_use]
+  pub fn with_avatars(mut self, avatars: bool) -> Html {
+    self.avatars = avatars;
+    self
+  }
+
+  fn contributor(&self, contributor: &Contributor) -> String {
+    let avatar = match &contributor.avatar_url {
+      Some(avatar_url) if self.avatars => format!(
+        "<img src=\"{}\" alt=\"\" width=\"20\" height=\"20\"> ",
+        Html::escape(avatar_url)
+      ),
+      _ => String::new(),
+    };
+    let name = match &contributor.url {
+      Some(url) => format!(
+        "<a href=\"{url}\">@{name}</a>",
+        url = Html::escape(url),
+        name = Html::escape(&contributor.name)
+      ),
+      None => Html::escape(&contributor.name),
+    };
+    format!(
+      "<li>{avatar}{name} {summary}</li>\n",
+      avatar = avatar,
+      name = name,
+      summary = Html::escape(&contributor.summary())
+    )
+  }
+
+  fn escape(text: &str) -> String {
+    text
+      .replace('&', "&amp;")
+      .replace('<', "&lt;")
+      .replace('>', "&gt;")
+      .replace('"', "&quot;")
+  }
+
+  fn entry(entry: &Entry) -> String {
+    let links = match &entry.pull_request {
+      Some(pull_request) => format!(
+        "<a href=\"{url}\">#{number}</a> <a href=\"https://github.com/{author}\">@{author}</a>",
+        url = Html::escape(&pull_request.url),
+        number = Html::escape(&pull_request.number),
+        author = Html::escape(&entry.author)
+      ),
+      None => format!(
+        "<a href=\"{url}\"><code>{short_hash}</code></a>",
+        url = Html::escape(&entry.url),
+        short_hash = entry.short_hash
+      ),
+    };
+    let merged_by = match merged_by(entry) {
+      Some(login) => format!(
+        " {label} <a href=\"https://github.com/{login}\">@{login}</a>",
+        label = Html::escape(&t!("changelog.merged_by")),
+        login = Html::escape(login)
+      ),
+      None => String::new(),
+    };
+    let issues: String = entry
+      .issues
+      .iter()
+      .map(|issue| {
+        let class = match issue.kind {
+          IssueKind::Issue => "issue",
+          IssueKind::PullRequest => "pull-request",
+          IssueKind::Jira => "jira",
+        };
+        format!(
+          " <a class=\"{class}\" href=\"{url}\">{label}</a>",
+          class = class,
+          url = Html::escape(&issue.url),
+          label = Html::escape(&issue.label)
+        )
+      })
+      .collect();
+    format!(
+      "<li>{message} {links}{merged_by}{issues}</li>\n",
+      merged_by = merged_by,
+      issues = issues,
+      message = Html::escape(entry.title()),
+      links = links
+    )
+  }
+
+  fn stats(stats: &DiffStats) -> String {
+    let mut html = format!("<details><summary>{}</summary>\n<ul>\n", stats.summary());
+    for path_stats in &stats.top_paths {
+      html.push_str(&format!(
+        "<li><code>{path}</code> +{insertions} -{deletions}</li>\n",
+        path = Html::escape(&path_stats.path),
+        insertions = path_stats.insertions,
+        deletions = path_stats.deletions
+      ));
+    }
+    html.push_str("</ul>\n</details>\n");
+    html
+  }
+}
+
+impl Renderer for Html {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    let mut html = format!(
+      "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+      title = Html::escape(&t!("changelog.title")),
+      style = Html::STYLE
+    );
+    for group in group_releases(packages) {
+      let title = match &group.release.compare_url {
+        Some(url) => format!(
+          "<a href=\"{url}\">{title}</a>",
+          url = Html::escape(url),
+          title = Html::escape(&group.release.title)
+        ),
+        None => Html::escape(&group.release.title),
+      };
+      html.push_str(&format!(
+        "<section>\n<h2>{title}</h2>\n<time>{date}</time>\n",
+        title = title,
+        date = Html::escape(&group.release.date)
+      ));
+      if let Some(summary) = &group.release.summary {
+        html.push_str(&format!("<p>{}</p>\n", Html::escape(summary)));
+      }
+      for (package, release) in &group.packages {
+        if release.entries.is_empty() && release.stats.is_none() {
+          continue;
+        }
+        let (entries, reverts) = split_reverts(&release.entries);
+        html.push_str(&format!("<h3>{}</h3>\n<ul>\n", Html::escape(package)));
+        for entry in entries {
+          html.push_str(&Html::entry(entry));
+        }
+        html.push_str("</ul>\n");
+        if !reverts.is_empty() {
+          html.push_str(&format!(
+            "<h4>{}</h4>\n<ul>\n",
+            Html::escape(&CommitTypes::heading("Reverts"))
+          ));
+          for entry in reverts {
+            html.push_str(&Html::entry(entry));
+          }
+          html.push_str("</ul>\n");
+        }
+        if let Some(stats) = &release.stats {
+          html.push_str(&Html::stats(stats));
+        }
+      }
+      let contributors = merge_contributors(
+        group
+          .packages
+          .iter()
+          .flat_map(|(_, release)| release.contributors.iter().cloned()),
+      );
+      if !contributors.is_empty() {
+        html.push_str(&format!(
+          "<h3>{}</h3>\n<ul>\n",
+          Html::escape(&CommitTypes::heading("Contributors"))
+        ));
+        for contributor in &contributors {
+          html.push_str(&self.contributor(contributor));
+        }
+        html.push_str("</ul>\n");
+      }
+      html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    merge_contributors, CommitTypes, Contributor, Entry, Html, IssueKind, IssueLink, OutputFormat,
+    PackageNotes, PullRequest, ReleaseNotes, Renderer,
+  };
+
+  fn entry(message: &str, pull_request: Option<&str>) -> Entry {
+    Entry {
+      message: message.to_string(),
+      hash: "0123456789abcdef".to_string(),
+      short_hash: "0123456".to_string(),
+      url: "https://github.com/ant-design/pro-components/commit/0123456".to_string(),
+      pull_request: pull_request.map(|number| PullRequest {
+        number: number.to_string(),
+        url: format!(
+          "https://github.com/ant-design/pro-components/pull/{}",
+          number
+        ),
+        ..PullRequest::default()
+      }),
+      author: "chenshuai2144".to_string(),
+      reverts: None,
+      issues: vec![],
+    }
+  }
+
+  fn revert(message: &str) -> Entry {
+    Entry {
+      reverts: Some("9999999999999999999999999999999999999999".to_string()),
+      ..entry(message, None)
+    }
+  }
+
+  fn packages() -> Vec<PackageNotes> {
+    let release = |entries| ReleaseNotes {
+      title: "@ant-design/pro-components@1.1.0".to_string(),
+      compare_url: None,
+      tag: "@ant-design/pro-components@1.1.0".to_string(),
+      date: "2021-05-01".to_string(),
+      summary: None,
+      entries,
+      contributors: vec![],
+      stats: None,
+    };
+    vec![
+      PackageNotes {
+        package: "components".to_string(),
+        releases: vec![release(vec![])],
+      },
+      PackageNotes {
+        package: "form".to_string(),
+        releases: vec![release(vec![
+          entry("feat(form): support <Segmented>", Some("42")),
+          Entry {
+            issues: vec![
+              IssueLink {
+                kind: IssueKind::Issue,
+                label: "#12".to_string(),
+                url: "https://github.com/ant-design/pro-components/issues/12".to_string(),
+              },
+              IssueLink {
+                kind: IssueKind::Jira,
+                label: "PROJ-7".to_string(),
+                url: "https://jira.example.com/browse/PROJ-7".to_string(),
+              },
+            ],
+            ..entry("fix(form): fix the label", None)
+          },
+        ])],
+      },
+    ]
+  }
+
+  fn render(format: &str) -> String {
+    let format: OutputFormat = format.parse().unwrap();
+    format.renderer().render(&packages()).unwrap()
+  }
+
+  #[test]
+  fn it_renders_markdown() {
+    assert_eq!(
+      render("markdown"),
+      "## @ant-design/pro-components@1.1.0\n\n`2021-05-01`\n\n\
+       * feat(form): support <Segmented>. [#42](https://github.com/ant-design/pro-components/pull/42) [@chenshuai2144](https://github.com/chenshuai2144)\n\
+       * fix(form): fix the label. [0123456](https://github.com/ant-design/pro-components/commit/0123456) [#12](https://github.com/ant-design/pro-components/issues/12) [PROJ-7](https://jira.example.com/browse/PROJ-7)\n"
+    );
+  }
+
+  /// The packages of a range, with the same title for every package.
+  fn range() -> Vec<PackageNotes> {
+    let mut packages = packages();
+    for package in &mut packages {
+      for release in &mut package.releases {
+        release.title = "v1.0.0...v1.1.0".to_string();
+        release.compare_url =
+          Some("https://github.com/ant-design/pro-components/compare/v1.0.0...v1.1.0".to_string());
+      }
+    }
+    packages
+  }
+
+  #[test]
+  fn it_links_ranges_to_the_comparison() {
+    let packages = range();
+    let url = "https://github.com/ant-design/pro-components/compare/v1.0.0...v1.1.0";
+    let render = |format: OutputFormat| format.renderer().render(&packages).unwrap();
+
+    assert!(render(OutputFormat::Markdown).starts_with(&format!(
+      "## [v1.0.0...v1.1.0]({})\n\n`2021-05-01`\n\n",
+      url
+    )));
+
+    let md_content = render(OutputFormat::KeepAChangelog);
+    assert!(md_content.contains("\n## [v1.0.0...v1.1.0] - 2021-05-01\n"));
+    assert!(md_content.ends_with(&format!("\n\n[v1.0.0...v1.1.0]: {}\n", url)));
+    assert!(!md_content.contains("## [["));
+
+    let html = render(OutputFormat::Html);
+    assert!(html.contains(&format!("<h2><a href=\"{}\">v1.0.0...v1.1.0</a></h2>", url)));
+
+    let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+    assert_eq!(json[0]["releases"][0]["title"], "v1.0.0...v1.1.0");
+    assert_eq!(json[0]["releases"][0]["compareUrl"], url);
+  }
+
+  #[test]
+  fn it_lists_reverts_last() {
+    let mut packages = packages();
+    packages[1].releases[0]
+      .entries
+      .insert(0, revert("Revert \"feat(form): add a toolbar\""));
+    let heading = format!("### {}\n", CommitTypes::heading("Reverts"));
+    for format in ["markdown", "keep-a-changelog"] {
+      let format: OutputFormat = format.parse().unwrap();
+      let md_content = format.renderer().render(&packages).unwrap();
+      let reverts = md_content.find(&heading).unwrap();
+      assert!(md_content.find("fix the label").unwrap() < reverts);
+      assert!(md_content.find("add a toolbar").unwrap() > reverts);
+    }
+    let html = OutputFormat::Html.renderer().render(&packages).unwrap();
+    assert!(html.find("<h4>").unwrap() < html.find("add a toolbar").unwrap());
+  }
+
+  #[test]
+  fn it_lists_contributors() {
+    let mut packages = packages();
+    packages[0].releases[0].contributors = vec![Contributor::github("chenshuai2144")];
+    packages[1].releases[0].contributors = merge_contributors([
+      Contributor::github("chenshuai2144"),
+      Contributor::git("someone"),
+      Contributor {
+        first_time: true,
+        ..Contributor::github("newcomer")
+      },
+      Contributor::git("someone"),
+    ]);
+    assert_eq!(
+      packages[1].releases[0]
+        .contributors
+        .iter()
+        .map(|contributor| (contributor.name.as_str(), contributor.commits))
+        .collect::<Vec<_>>(),
+      vec![("someone", 2), ("chenshuai2144", 1), ("newcomer", 1)]
+    );
+
+    let heading = format!("### {}\n", CommitTypes::heading("Contributors"));
+    let md_content = OutputFormat::Markdown.renderer().render(&packages).unwrap();
+    assert_eq!(md_content.matches(&heading).count(), 2);
+    assert!(md_content.contains("* [@chenshuai2144](https://github.com/chenshuai2144): "));
+    assert!(md_content.contains(&format!(
+      "* someone: {}\n",
+      packages[1].releases[0].contributors[0].summary()
+    )));
+
+    let md_content = OutputFormat::KeepAChangelog
+      .renderer()
+      .render(&packages)
+      .unwrap();
+    assert_eq!(md_content.matches(&heading).count(), 1);
+    let mut chenshuai2144 = Contributor::github("chenshuai2144");
+    chenshuai2144.commits = 2;
+    assert!(md_content.contains(&format!(
+      "- [@chenshuai2144](https://github.com/chenshuai2144): {}\n",
+      chenshuai2144.summary()
+    )));
+    assert!(md_content.contains(&format!(
+      "🎉 {}",
+      crate::i18n::t!("changelog.first_contribution")
+    )));
+
+    let html = Html::default()
+      .with_avatars(true)
+      .render(&packages)
+      .unwrap();
+    assert!(html.contains("<img src=\"https://github.com/newcomer.png?size=40\""));
+    assert!(!OutputFormat::Html
+      .renderer()
+      .render(&packages)
+      .unwrap()
+      .contains("<img"));
+  }
+
+  #[test]
+  fn it_renders_keep_a_changelog() {
+    let md_content = render("keep-a-changelog");
+    assert!(md_content.contains("\n## [@ant-design/pro-components@1.1.0] - 2021-05-01\n"));
+    let heading = |section| format!("### {}\n", CommitTypes::heading(section));
+    let added = md_content.find(&heading("Added")).unwrap();
+    let fixed = md_content.find(&heading("Fixed")).unwrap();
+    assert!(added < fixed);
+    assert!(md_content.contains("- **form**: fix(form): fix the label ("));
+    assert!(!md_content.contains(&heading("Changed")));
+  }
+
+  #[test]
+  fn it_renders_data_formats() {
+    let json: serde_json::Value = serde_json::from_str(&render("json")).unwrap();
+    assert_eq!(
+      json[1]["releases"][0]["entries"][0]["pullRequest"]["number"],
+      "42"
+    );
+    assert_eq!(json[1]["releases"][0]["entries"][1]["shortHash"], "0123456");
+
+    let yaml: serde_json::Value = serde_yaml::from_str(&render("yaml")).unwrap();
+    assert_eq!(yaml, json);
+
+    let html = render("html");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("support &lt;Segmented&gt;"));
+    assert!(!html.contains("<h3>components</h3>"));
+    assert!(
+      html.contains("<a class=\"jira\" href=\"https://jira.example.com/browse/PROJ-7\">PROJ-7</a>")
+    );
+  }
+
+  #[test]
+  fn it_maps_commit_types() {
+    let commit_types = CommitTypes::default()
+      .with_sections([("perf", "Changed"), ("fix", "Security")])
+      .unwrap();
+    assert_eq!(commit_types.section(&entry("fix: xss", None)), "Security");
+    assert_eq!(commit_types.section(&entry("feat: a", None)), "Added");
+    assert_eq!(commit_types.section(&entry("docs: a", None)), "Changed");
+    assert!(CommitTypes::default()
+      .with_sections([("feat", "Features")])
+      .is_err());
+  }
+
+  #[test]
+  fn it_uses_pull_request_details() {
+    let mut labeled = entry("feat: support dark mode (#7)", Some("7"));
+    let pull_request = labeled.pull_request.as_mut().unwrap();
+    pull_request.title = Some("Dark mode for every form".to_string());
+    pull_request.labels = vec!["Bug".to_string(), "security".to_string()];
+
+    let commit_types = CommitTypes::default()
+      .with_labels([("security", "Security")])
+      .unwrap();
+    assert_eq!(commit_types.section(&labeled), "Fixed");
+    labeled.pull_request.as_mut().unwrap().labels = vec!["docs".to_string()];
+    assert_eq!(commit_types.section(&labeled), "Added");
+    assert!(CommitTypes::default()
+      .with_labels([("bug", "Bugs")])
+      .is_err());
+
+    let packages = vec![PackageNotes {
+      package: "form".to_string(),
+      releases: vec![ReleaseNotes {
+        entries: vec![labeled],
+        ..packages()[1].releases[0].clone()
+      }],
+    }];
+    let md_content = OutputFormat::Markdown.renderer().render(&packages).unwrap();
+    assert!(md_content.contains("* Dark mode for every form. [#7]("));
+  }
+
+  #[test]
+  fn it_shows_who_merged_pull_requests() {
+    let mut packages = packages();
+    packages[1].releases[0].entries[0]
+      .pull_request
+      .as_mut()
+      .unwrap()
+      .merged_by = Some("someone".to_string());
+    let merged_by = crate::i18n::t!("changelog.merged_by");
+    let render = |format: OutputFormat| format.renderer().render(&packages).unwrap();
+
+    let link = format!(
+      "[@chenshuai2144](https://github.com/chenshuai2144) {} [@someone](https://github.com/someone)\n",
+      merged_by
+    );
+    assert!(render(OutputFormat::Markdown).contains(&link));
+    assert!(render(OutputFormat::KeepAChangelog).contains(&format!("{})\n", link.trim_end())));
+    assert!(render(OutputFormat::Html).contains(&format!(
+      "@chenshuai2144</a> {} <a href=\"https://github.com/someone\">@someone</a></li>",
+      merged_by
+    )));
+    assert_eq!(
+      render(OutputFormat::Markdown).matches(&merged_by).count(),
+      1
+    );
+  }
+
+  #[test]
+  fn it_parses_commit_kinds() {
+    assert_eq!(entry("feat(form): a", None).kind(), Some("feat"));
+    assert_eq!(entry("fix!: a", None).kind(), Some("fix"));
+    assert_eq!(entry("Merge branch 'master'", None).kind(), None);
+    assert_eq!(entry("chore: release: 1.0", None).kind(), Some("chore"));
+    assert!("text".parse::<OutputFormat>().is_err());
+  }
+}
@@ -0,0 +1,396 @@
+use handlebars::{no_escape, Handlebars};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::git::DiffStats;
+use super::issues::IssueLink;
+use super::render::{
+  CommitTypes, Contributor, Entry, PackageNotes, PullRequest, ReleaseNotes, Renderer,
+};
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// The template of the markdown output, a starting point for custom
+/// templates.
+pub const DEFAULT_TEMPLATE: &str = include_str!("templates/default.hbs");
+
+/// The name the template is registered under.
+const NAME: &str = "changelog";
+
+/// A commit author with a link to the GitHub profile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct AuthorContext<'a> {
+  name: &'a str,
+  url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryContext<'a> {
+  message: &'a str,
+  /// The pull request title when known, the message otherwise.
+  title: &'a str,
+  /// The conventional commit type such as `feat`.
+  kind: Option<&'a str>,
+  hash: &'a str,
+  short_hash: &'a str,
+  url: &'a str,
+  pull_request: Option<&'a PullRequest>,
+  author: &'a str,
+  author_url: String,
+  reverts: Option<&'a str>,
+  issues: &'a [IssueLink],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributorContext<'a> {
+  #[serde(flatten)]
+  contributor: &'a Contributor,
+  /// The localized number of commits, and the first contribution note.
+  summary: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReleaseContext<'a> {
+  title: &'a str,
+  compare_url: Option<&'a str>,
+  tag: &'a str,
+  date: &'a str,
+  summary: Option<&'a str>,
+  entries: Vec<EntryContext<'a>>,
+  /// Reverts of commits of earlier releases, left out of `entries`.
+  reverts: Vec<EntryContext<'a>>,
+  /// The authors of the entries, in order of appearance.
+  authors: Vec<AuthorContext<'a>>,
+  contributors: Vec<ContributorContext<'a>>,
+  stats: Option<&'a DiffStats>,
+  /// The localized number of files, insertions and deletions.
+  stats_summary: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PackageContext<'a> {
+  package: &'a str,
+  /// The first package carries the release headings in the markdown
+  /// output.
+  first: bool,
+  releases: Vec<ReleaseContext<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Context<'a> {
+  packages: Vec<PackageContext<'a>>,
+  /// The localized heading of the reverts.
+  reverts_heading: String,
+  contributors_heading: String,
+  /// The localized text before who merged a pull request.
+  merged_by_label: String,
+}
+
+fn author_url(author: &str) -> String {
+  format!("https://github.com/{}", author)
+}
+
+impl<'a> ReleaseContext<'a> {
+  fn new(release: &'a ReleaseNotes) -> ReleaseContext<'a> {
+    let mut authors: Vec<AuthorContext> = vec![];
+    for entry in &release.entries {
+      if !authors.iter().any(|author| author.name == entry.author) {
+        authors.push(AuthorContext {
+          name: &entry.author,
+          url: author_url(&entry.author),
+        });
+      }
+    }
+    ReleaseContext {
+      title: &release.title,
+      compare_url: release.compare_url.as_deref(),
+      tag: &release.tag,
+      date: &release.date,
+      summary: release.summary.as_deref(),
+      entries: release
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_revert())
+        .map(EntryContext::new)
+        .collect(),
+      reverts: release
+        .entries
+        .iter()
+        .filter(|entry| entry.is_revert())
+        .map(EntryContext::new)
+        .collect(),
+      authors,
+      contributors: release
+        .contributors
+        .iter()
+        .map(|contributor| ContributorContext {
+          contributor,
+          summary: contributor.summary(),
+        })
+        .collect(),
+      stats: release.stats.as_ref(),
+      stats_summary: release.stats.as_ref().map(DiffStats::summary),
+    }
+  }
+}
+
+impl<'a> EntryContext<'a> {
+  fn new(entry: &'a Entry) -> EntryContext<'a> {
+    EntryContext {
+      message: &entry.message,
+      title: entry.title(),
+      kind: entry.kind(),
+      hash: &entry.hash,
+      short_hash: &entry.short_hash,
+      url: &entry.url,
+      pull_request: entry.pull_request.as_ref(),
+      author: &entry.author,
+      author_url: author_url(&entry.author),
+      reverts: entry.reverts.as_deref(),
+      issues: &entry.issues,
+    }
+  }
+}
+
+/// A renderer driven by a user provided
+/// [Handlebars](https://handlebarsjs.com) template.
+///
+/// The template gets a `packages` list, each package has a `package` name,
+/// `first` set on the first package and its `releases`. A release has a
+/// `title`, the `compareUrl` of ranges, `tag`, `date`, `summary`, `entries`,
+/// `reverts`, `authors`, `contributors`, `stats` and its localized
+/// `statsSummary`. An entry has a
+/// `message`, the `title` shown for it, `kind`, `hash`, `shortHash`, commit
+/// `url`, `pullRequest` with a `number`, `url`, `title`, `labels` and
+/// `mergedBy`, `author`, `authorUrl`, the `reverts` hash for reverts and the
+/// `issues` with a `kind`, `label` and `url`. A contributor has a `name`,
+/// `url`, `avatarUrl`, `commits`, `firstTime` and a localized `summary`. The
+/// localized texts are the `revertsHeading`, `contributorsHeading` and
+/// `mergedByLabel`. Nothing is HTML escaped.
+pub struct Template {
+  registry: Handlebars<'static>,
+  /// The file extension of the output, `md` unless the template file is
+  /// named like `changelog.html.hbs`.
+  extension: String,
+}
+
+impl Template {
+  /// Compile a template.
+  pub fn new(source: &str) -> crate::Result<Template> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry.set_strict_mode(false);
+    registry
+      .register_template_string(NAME, source)
+      .map_err(|err| ErrorKind::Config(t!("changelog.template_invalid", err)))?;
+    Ok(Template {
+      registry,
+      extension: "md".to_string(),
+    })
+  }
+
+  /// Read and compile a template file.
+  pub fn from_file(path: &str) -> crate::Result<Template> {
+    let source = fs::read_to_string(path)
+      .map_err(|err| ErrorKind::Config(t!("changelog.template_read_failed", path, err)))?;
+    let mut template = Template::new(&source)?;
+    let path = Path::new(path);
+    if let Some(extension) = path
+      .file_stem()
+      .map(Path::new)
+      .and_then(Path::extension)
+      .and_then(|extension| extension.to_str())
+    {
+      template.extension = extension.to_string();
+    }
+    Ok(template)
+  }
+
+  /// The file extension of the rendered document.
+  #[must_use]
+  pub fn extension(&self) -> &str {
+    &self.extension
+  }
+}
+
+impl Default for Template {
+  fn default() -> Template {
+    Template::new(DEFAULT_TEMPLATE).expect("the default template is valid")
+  }
+}
+
+impl Renderer for Template {
+  fn render(&self, packages: &[PackageNotes]) -> crate::Result<String> {
+    let context = Context {
+      packages: packages
+        .iter()
+        .enumerate()
+        .map(|(index, package)| PackageContext {
+          package: &package.package,
+          first: index == 0,
+          releases: package.releases.iter().map(ReleaseContext::new).collect(),
+        })
+        .collect(),
+      reverts_heading: CommitTypes::heading("Reverts"),
+      contributors_heading: CommitTypes::heading("Contributors"),
+      merged_by_label: t!("changelog.merged_by"),
+    };
+    self
+      .registry
+      .render(NAME, &context)
+      .map_err(|err| ErrorKind::Config(t!("changelog.render_failed", "template", err)).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Template;
+  use crate::changelog::git::{DiffStats, PathStats};
+  use crate::changelog::issues::{IssueKind, IssueLink};
+  use crate::changelog::render::{
+    merge_contributors, Contributor, Entry, Markdown, PackageNotes, PullRequest, ReleaseNotes,
+    Renderer,
+  };
+  use crate::i18n::t;
+
+  fn entry(message: &str, author: &str, pull_request: Option<&str>) -> Entry {
+    Entry {
+      message: message.to_string(),
+      hash: "0123456789abcdef".to_string(),
+      short_hash: "0123456".to_string(),
+      url: "https://github.com/ant-design/pro-components/commit/0123456".to_string(),
+      pull_request: pull_request.map(|number| PullRequest {
+        number: number.to_string(),
+        url: format!(
+          "https://github.com/ant-design/pro-components/pull/{}",
+          number
+        ),
+        ..PullRequest::default()
+      }),
+      author: author.to_string(),
+      reverts: None,
+      issues: vec![],
+    }
+  }
+
+  fn release(version: &str, entries: Vec<Entry>, stats: Option<DiffStats>) -> ReleaseNotes {
+    let contributors = merge_contributors(entries.iter().map(|entry| Contributor {
+      first_time: entry.author == "someone",
+      ..Contributor::github(&entry.author)
+    }));
+    ReleaseNotes {
+      title: format!("@ant-design/pro-components@{}", version),
+      compare_url: None,
+      tag: format!("@ant-design/pro-components@{}", version),
+      date: "2021-05-01".to_string(),
+      summary: Some("The first release.".to_string()),
+      entries,
+      contributors,
+      stats,
+    }
+  }
+
+  fn packages() -> Vec<PackageNotes> {
+    let stats = DiffStats {
+      files_changed: 2,
+      insertions: 10,
+      deletions: 3,
+      top_paths: vec![PathStats {
+        path: "packages/form/src/index.tsx".to_string(),
+        insertions: 8,
+        deletions: 1,
+      }],
+    };
+    vec![
+      PackageNotes {
+        package: "components".to_string(),
+        releases: vec![
+          release("1.1.0", vec![], Some(stats.clone())),
+          release("1.0.0", vec![], None),
+        ],
+      },
+      PackageNotes {
+        package: "form".to_string(),
+        releases: vec![
+          release(
+            "1.1.0",
+            vec![
+              {
+                let mut merged = entry(
+                  "feat(form): support <Segmented>",
+                  "chenshuai2144",
+                  Some("42"),
+                );
+                merged.pull_request.as_mut().unwrap().merged_by = Some("someone".to_string());
+                merged
+              },
+              Entry {
+                issues: vec![IssueLink {
+                  kind: IssueKind::PullRequest,
+                  label: "org/repo#34".to_string(),
+                  url: "https://github.com/org/repo/pull/34".to_string(),
+                }],
+                ..entry("fix(form): fix the label", "chenshuai2144", None)
+              },
+              Entry {
+                reverts: Some("9999999999999999999999999999999999999999".to_string()),
+                ..entry("Revert \"feat(form): add a toolbar\"", "someone", None)
+              },
+            ],
+            Some(stats),
+          ),
+          release(
+            "1.0.0",
+            vec![entry("feat(form): add form", "someone", Some("7"))],
+            None,
+          ),
+        ],
+      },
+    ]
+  }
+
+  #[test]
+  fn it_reproduces_the_markdown_output() {
+    let packages = packages();
+    let md_content = Template::default().render(&packages).unwrap();
+    assert!(md_content.contains(&format!(
+      "<summary>form: {}</summary>",
+      t!("changelog.stats", 2, 10, 3)
+    )));
+    assert_eq!(md_content, Markdown.render(&packages).unwrap());
+  }
+
+  #[test]
+  fn it_reproduces_the_markdown_output_of_ranges() {
+    let mut packages = packages();
+    for package in &mut packages {
+      package.releases.truncate(1);
+      package.releases[0].title = "v1.0.0...v1.1.0".to_string();
+      package.releases[0].compare_url =
+        Some("https://github.com/ant-design/pro-components/compare/v1.0.0...v1.1.0".to_string());
+    }
+    let md_content = Template::default().render(&packages).unwrap();
+    assert!(md_content.starts_with(
+      "## [v1.0.0...v1.1.0](https://github.com/ant-design/pro-components/compare/v1.0.0...v1.1.0)\n"
+    ));
+    assert_eq!(md_content, Markdown.render(&packages).unwrap());
+  }
+
+  #[test]
+  fn it_renders_custom_templates() {
+    let template = Template::new(
+      "{{#each packages}}{{#each releases}}{{tag}}:{{#each authors}} {{name}}{{/each}}\n{{/each}}{{/each}}",
+    )
+    .unwrap();
+    assert_eq!(
+      template.render(&packages()[1..]).unwrap(),
+      "@ant-design/pro-components@1.1.0: chenshuai2144 someone\n@ant-design/pro-components@1.0.0: someone\n"
+    );
+    assert!(Template::new("{{#each packages}}").is_err());
+  }
+}
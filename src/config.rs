@@ -0,0 +1,437 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::changelog::filter::{
+  BotAuthors, CommitFilters, ExcludedHashes, ExcludedTypes, RevertPairs, SkipMarker,
+};
+use crate::changelog::git::{DateOptions, LatestRelease, PrereleasePolicy, TagPattern, Timezone};
+use crate::changelog::issues::JiraKeys;
+use crate::changelog::render::{CommitTypes, OutputFormat};
+use crate::changelog::CommitMatch;
+use crate::i18n::{t, Locale};
+use crate::npm::preflight::{RuleLevel, RULES};
+use crate::npm::BackendKind;
+use crate::ErrorKind;
+
+/// 按照顺序查找的配置文件，都没有的话使用 package.json 中的 doctor 字段
+pub const CONFIG_FILES: [&str; 2] = ["doctor.config.json", ".doctorrc"];
+
+/// changelog 相关的配置，和 ChangelogOptions 一一对应
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ChangelogConfig {
+  /// 生成的 changelog 所在的目录，默认为 .changelogs
+  pub output: Option<String>,
+  pub format: Option<String>,
+  pub template: Option<String>,
+  pub commit_match: Option<String>,
+  pub first_parent: Option<bool>,
+  pub no_merges: Option<bool>,
+  pub diff_stats: Option<bool>,
+  pub prerelease: Option<String>,
+  pub latest: Option<String>,
+  pub date_format: Option<String>,
+  pub timezone: Option<String>,
+  pub tag_summary: Option<bool>,
+  pub avatars: Option<bool>,
+  pub pr_details: Option<bool>,
+  #[serde(default)]
+  pub exclude: ExcludeConfig,
+  pub jira: Option<JiraConfig>,
+}
+
+/// jira 这样的 issue 的配置，commit 信息中的 key 会输出为链接
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JiraConfig {
+  /// key 的正则，默认为 [A-Z][A-Z0-9]+-[0-9]+
+  pub pattern: Option<String>,
+  /// 链接的模板，{key} 会替换为 key，比如 https://jira.example.com/browse/{key}
+  pub url: String,
+}
+
+impl JiraConfig {
+  /* 识别 key 的规则，已经在读取时检查过 */
+  pub fn keys(&self) -> crate::Result<JiraKeys> {
+    JiraKeys::new(self.pattern.as_deref(), &self.url)
+  }
+}
+
+/// 过滤 commit 的配置，没有配置的规则使用默认值，配置为空列表时关闭这个规则
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExcludeConfig {
+  /// commit 信息中带有这些标记时跳过，默认为 [skip changelog] 和 [changelog skip]
+  pub markers: Option<Vec<String>>,
+  /// 跳过的 commit 类型，默认为 chore、docs 和 test
+  pub types: Option<Vec<String>>,
+  /// 跳过的作者，默认为 dependabot 和 renovate，以 [bot] 结尾的作者也会被跳过
+  pub authors: Option<Vec<String>>,
+  /// 同一个版本中的 commit 和 revert 它的 commit 都跳过，默认为 true
+  pub reverts: Option<bool>,
+  /// 跳过的 commit hash，可以是缩写
+  #[serde(default)]
+  pub commits: Vec<String>,
+}
+
+impl ExcludeConfig {
+  /**
+   * 按照配置生成过滤 commit 的规则
+   */
+  pub fn filters(&self) -> CommitFilters {
+    let mut filters = CommitFilters::empty();
+    match &self.markers {
+      Some(markers) if markers.is_empty() => {}
+      Some(markers) => filters = filters.with_filter(SkipMarker::new(markers.clone())),
+      None => filters = filters.with_filter(SkipMarker::default()),
+    }
+    match &self.types {
+      Some(types) if types.is_empty() => {}
+      Some(types) => filters = filters.with_filter(ExcludedTypes::new(types.clone())),
+      None => filters = filters.with_filter(ExcludedTypes::default()),
+    }
+    match &self.authors {
+      Some(authors) if authors.is_empty() => {}
+      Some(authors) => filters = filters.with_filter(BotAuthors::new(authors.clone())),
+      None => filters = filters.with_filter(BotAuthors::default()),
+    }
+    if self.reverts.unwrap_or(true) {
+      filters = filters.with_filter(RevertPairs::default());
+    }
+    if !self.commits.is_empty() {
+      filters = filters.with_filter(ExcludedHashes::new(self.commits.clone()));
+    }
+    filters
+  }
+}
+
+/// 代码托管平台的配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ForgeConfig {
+  /// 仓库的网页地址，比如 https://github.com/ant-design/pro-components
+  pub url: Option<String>,
+  /// api 的地址，默认为 https://api.github.com
+  pub api_url: Option<String>,
+  /// 仓库的名字，比如 ant-design/pro-components
+  pub repo: Option<String>,
+}
+
+/// npm 相关的配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NpmConfig {
+  /// 会覆盖 .npmrc 中的 registry
+  pub registry: Option<String>,
+  pub backend: Option<String>,
+}
+
+/// 发布前检查的配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LintConfig {
+  /// 规则 -> off、warning 或者 error
+  #[serde(default)]
+  pub rules: BTreeMap<String, String>,
+}
+
+/// doctor 的项目配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Config {
+  /// 需要生成 changelog 的包，不带前缀，默认为 workspace 中所有的包
+  pub packages: Option<Vec<String>>,
+  /// 包名的前缀，默认为 workspace 中所有包名共同的前缀，比如 @ant-design/pro-
+  pub package_prefix: Option<String>,
+  /// 包所在的目录，包的路径是 <packagesDir>/<package>/，默认从 workspace 的配置中找到每个包的目录
+  pub packages_dir: Option<String>,
+  /// git tag 的命名方式，changelog 和 dist-tag 共用
+  pub tag_pattern: Option<String>,
+  /// 输出的语言，zh-CN 或者 en-US，环境变量 DOCTOR_LOCALE 优先于这里的配置
+  pub locale: Option<String>,
+  /// commit 的类型 -> keep-a-changelog 的分组，比如 { "perf": "Changed" }
+  #[serde(default)]
+  pub commit_types: BTreeMap<String, String>,
+  /// pr 的 label -> keep-a-changelog 的分组，比如 { "breaking": "Removed" }，优先于 commit 的类型
+  /// 只在开启 changelog.prDetails 时生效
+  #[serde(default)]
+  pub label_sections: BTreeMap<String, String>,
+  #[serde(default)]
+  pub changelog: ChangelogConfig,
+  #[serde(default)]
+  pub forge: ForgeConfig,
+  #[serde(default)]
+  pub npm: NpmConfig,
+  #[serde(default)]
+  pub lint: LintConfig,
+  /// 配置所在的文件，用于错误信息
+  #[serde(skip)]
+  pub source: Option<String>,
+}
+
+/* 取出配置错误的信息，避免重复的前缀 */
+fn config_message(err: crate::Error) -> String {
+  match err.kind() {
+    ErrorKind::Config(message) => message.clone(),
+    kind => kind.message(),
+  }
+}
+
+impl Config {
+  /**
+   * 从仓库的根目录读取配置，没有配置文件时返回默认的配置
+   */
+  pub fn load(repo: &str) -> crate::Result<Config> {
+    let root = Path::new(repo);
+    for file in CONFIG_FILES {
+      let path = root.join(file);
+      if let Ok(data) = fs::read_to_string(&path) {
+        let config = serde_json::from_str::<Config>(&data)
+          .map_err(|err| ErrorKind::Config(t!("config.parse_failed", file, err)))?;
+        return config.with_source(file);
+      }
+    }
+
+    if let Ok(data) = fs::read_to_string(root.join("package.json")) {
+      let source = "package.json#doctor";
+      let package_json = serde_json::from_str::<Value>(&data)
+        .map_err(|err| ErrorKind::Config(t!("config.parse_failed", "package.json", err)))?;
+      if let Some(doctor) = package_json.get("doctor") {
+        let config = Config::deserialize(doctor)
+          .map_err(|err| ErrorKind::Config(t!("config.parse_failed", source, err)))?;
+        return config.with_source(source);
+      }
+    }
+
+    Ok(Config::default())
+  }
+
+  fn with_source(mut self, source: &str) -> crate::Result<Config> {
+    self.source = Some(source.to_string());
+    self.validate()?;
+    Ok(self)
+  }
+
+  /* 检查一个字段能不能解析，错误信息带上文件和字段的名字 */
+  fn check<T: FromStr<Err = crate::Error>>(
+    &self,
+    field: &str,
+    value: Option<&str>,
+  ) -> crate::Result<()> {
+    match value.map(str::parse::<T>) {
+      Some(Err(err)) => Err(self.error(field, config_message(err))),
+      _ => Ok(()),
+    }
+  }
+
+  fn error(&self, field: &str, message: String) -> crate::Error {
+    let source = match &self.source {
+      Some(source) => source.clone(),
+      None => t!("config.default_source"),
+    };
+    ErrorKind::Config(t!("config.field_invalid", source, field, message)).into()
+  }
+
+  /**
+   * 检查所有的配置，读取的时候就报错，而不是用到的时候
+   */
+  pub fn validate(&self) -> crate::Result<()> {
+    if let Some(packages) = &self.packages {
+      if packages.is_empty() {
+        return Err(self.error("packages", t!("config.packages_empty")));
+      }
+    }
+    self.check::<TagPattern>("tagPattern", self.tag_pattern.as_deref())?;
+    self.check::<Locale>("locale", self.locale.as_deref())?;
+    CommitTypes::default()
+      .with_sections(Config::pairs(&self.commit_types))
+      .map_err(|err| self.error("commitTypes", config_message(err)))?;
+    CommitTypes::default()
+      .with_labels(Config::pairs(&self.label_sections))
+      .map_err(|err| self.error("labelSections", config_message(err)))?;
+
+    let changelog = &self.changelog;
+    self.check::<OutputFormat>("changelog.format", changelog.format.as_deref())?;
+    self.check::<CommitMatch>("changelog.commitMatch", changelog.commit_match.as_deref())?;
+    self.check::<PrereleasePolicy>("changelog.prerelease", changelog.prerelease.as_deref())?;
+    self.check::<LatestRelease>("changelog.latest", changelog.latest.as_deref())?;
+    self.check::<Timezone>("changelog.timezone", changelog.timezone.as_deref())?;
+    for hash in &changelog.exclude.commits {
+      if !(4..=40).contains(&hash.len()) || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(self.error(
+          "changelog.exclude.commits",
+          t!("changelog.exclude_commit_invalid", hash),
+        ));
+      }
+    }
+    if let Some(jira) = &changelog.jira {
+      jira
+        .keys()
+        .map_err(|err| self.error("changelog.jira", config_message(err)))?;
+    }
+    if let Some(date_format) = &changelog.date_format {
+      DateOptions::new(date_format, Timezone::Utc)
+        .map_err(|err| self.error("changelog.dateFormat", config_message(err)))?;
+    }
+
+    self.check::<BackendKind>("npm.backend", self.npm.backend.as_deref())?;
+    for (rule, level) in &self.lint.rules {
+      if !RULES.contains(&rule.as_str()) {
+        return Err(self.error(
+          "lint.rules",
+          t!("config.rule_unknown", rule, RULES.join(", ")),
+        ));
+      }
+      self.check::<RuleLevel>(&format!("lint.rules.{}", rule), Some(level))?;
+    }
+    Ok(())
+  }
+
+  /* keep-a-changelog 中 commit 的类型和 pr 的 label 对应的分组，已经在读取时检查过 */
+  pub fn commit_types(&self) -> crate::Result<CommitTypes> {
+    CommitTypes::default()
+      .with_sections(Config::pairs(&self.commit_types))?
+      .with_labels(Config::pairs(&self.label_sections))
+  }
+
+  fn pairs(map: &BTreeMap<String, String>) -> impl Iterator<Item = (&str, &str)> {
+    map
+      .iter()
+      .map(|(key, value)| (key.as_str(), value.as_str()))
+  }
+
+  /* 配置的语言，已经在读取时检查过 */
+  pub fn locale(&self) -> crate::Result<Option<Locale>> {
+    self.locale.as_deref().map(str::parse).transpose()
+  }
+
+  /* 规则 -> 级别，已经在读取时检查过 */
+  pub fn rule_levels(&self) -> crate::Result<Vec<(String, RuleLevel)>> {
+    self
+      .lint
+      .rules
+      .iter()
+      .map(|(rule, level)| Ok((rule.clone(), level.parse()?)))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use tempfile::TempDir;
+
+  use super::{config_message, Config};
+  use crate::i18n::t;
+  use crate::ErrorKind;
+
+  fn load(files: &[(&str, &str)]) -> crate::Result<Config> {
+    let dir = TempDir::new().unwrap();
+    for (file, content) in files {
+      fs::write(dir.path().join(file), content).unwrap();
+    }
+    Config::load(&dir.path().display().to_string())
+  }
+
+  fn error_message(result: crate::Result<Config>) -> String {
+    match result.err().unwrap().kind() {
+      ErrorKind::Config(message) => message.clone(),
+      kind => panic!("unexpected error {}", kind),
+    }
+  }
+
+  #[test]
+  fn it_loads_config_files_in_order() {
+    let config = load(&[
+      ("doctor.config.json", r#"{ "packages": ["form"] }"#),
+      (".doctorrc", r#"{ "packages": ["table"] }"#),
+      ("package.json", r#"{ "doctor": { "packages": ["card"] } }"#),
+    ])
+    .unwrap();
+    assert_eq!(config.packages, Some(vec!["form".to_string()]));
+    assert_eq!(config.source.as_deref(), Some("doctor.config.json"));
+
+    let config = load(&[(
+      "package.json",
+      r#"{ "name": "root", "doctor": { "changelog": { "format": "json" }, "lint": { "rules": { "missing-entry": "off" } } } }"#,
+    )])
+    .unwrap();
+    assert_eq!(config.changelog.format.as_deref(), Some("json"));
+    assert_eq!(config.rule_levels().unwrap().len(), 1);
+
+    let config = load(&[("package.json", r#"{ "name": "root" }"#)]).unwrap();
+    assert!(config.source.is_none());
+  }
+
+  #[test]
+  fn it_validates_config() {
+    assert!(
+      error_message(load(&[(".doctorrc", r#"{ "change_log": {} }"#)])).starts_with(&t!(
+        "config.parse_failed",
+        ".doctorrc",
+        "unknown field `change_log`"
+      ))
+    );
+    assert_eq!(
+      error_message(load(&[(
+        "doctor.config.json",
+        r#"{ "changelog": { "format": "pdf" } }"#
+      )])),
+      t!(
+        "config.field_invalid",
+        "doctor.config.json",
+        "changelog.format",
+        t!("changelog.format_invalid", "pdf")
+      )
+    );
+    assert!(
+      error_message(load(&[("doctor.config.json", r#"{ "locale": "fr-FR" }"#)])).contains("locale")
+    );
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "lint": { "rules": { "no-such-rule": "off" } } }"#
+    )]))
+    .contains("lint.rules"));
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "commitTypes": { "perf": "Faster" } }"#
+    )]))
+    .contains("commitTypes"));
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "labelSections": { "bug": "Bugs" } }"#
+    )]))
+    .contains("labelSections"));
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "tagPattern": "v{name}" }"#
+    )]))
+    .contains("tagPattern"));
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "changelog": { "exclude": { "commits": ["not-a-hash"] } } }"#
+    )]))
+    .contains("changelog.exclude.commits"));
+    assert!(error_message(load(&[(
+      "doctor.config.json",
+      r#"{ "changelog": { "jira": { "url": "https://jira.example.com/browse/" } } }"#
+    )]))
+    .contains("changelog.jira"));
+  }
+
+  #[test]
+  fn it_localizes_nested_errors() {
+    assert_eq!(
+      config_message(ErrorKind::Config("bad".to_string()).into()),
+      "bad"
+    );
+    assert_eq!(
+      config_message(ErrorKind::NoTags.into()),
+      t!("error.no_tags")
+    );
+  }
+}
@@ -2,6 +2,8 @@
 use std::fmt::{self, Display};
 use std::result;
 
+use crate::i18n::t;
+
 /// A specialized [`Result`] type for this crate's operations.
 ///
 /// This is generally used to avoid writing out [Error] directly and
@@ -33,6 +35,18 @@ pub enum ErrorKind {
   /// An error caused by a failed filesystem operation.
   #[fail(display = "An error occured accessing the disk.")]
   Fs,
+  /// An error caused by a missing, invalid or expired one-time password.
+  #[fail(display = "An error occurred with the one-time password: {}", _0)]
+  Otp(String),
+  /// An error caused by a failed npm command.
+  #[fail(display = "An error occurred running npm: {}", _0)]
+  Npm(String),
+  /// An error caused by a failed request to the npm registry.
+  #[fail(display = "An error occurred requesting the npm registry: {}", _0)]
+  Registry(String),
+  /// An error caused by an invalid option value.
+  #[fail(display = "Invalid option: {}", _0)]
+  Config(String),
   /// Any error not part of this list.
   #[fail(display = "Generic error.")]
   Other,
@@ -46,6 +60,24 @@ pub struct Error {
   inner: Context<ErrorKind>,
 }
 
+impl ErrorKind {
+  /// The description of the error in the current locale, the `Display`
+  /// implementation is always English.
+  pub fn message(&self) -> String {
+    match self {
+      ErrorKind::Log => t!("error.log"),
+      ErrorKind::Git => t!("error.git"),
+      ErrorKind::NoTags => t!("error.no_tags"),
+      ErrorKind::Fs => t!("error.fs"),
+      ErrorKind::Otp(message) => t!("error.otp", message),
+      ErrorKind::Npm(message) => t!("error.npm", message),
+      ErrorKind::Registry(message) => t!("error.registry", message),
+      ErrorKind::Config(message) => t!("error.config", message),
+      ErrorKind::Other => t!("error.other"),
+    }
+  }
+}
+
 impl Error {
   /// Access the [`ErrorKind`] member.
   ///
@@ -67,7 +99,7 @@ impl Fail for Error {
 
 impl Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    Display::fmt(&self.inner, f)
+    f.write_str(&self.kind().message())
   }
 }
 
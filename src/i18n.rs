@@ -0,0 +1,659 @@
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::ErrorKind;
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+  ZhCn,
+  EnUs,
+}
+
+/// 还没有设置语言，第一次使用时从环境变量推断
+const UNSET: u8 = u8::MAX;
+
+static LOCALE: AtomicU8 = AtomicU8::new(UNSET);
+
+impl Locale {
+  /**
+   * 按照 DOCTOR_LOCALE、配置文件中的 locale、LC_ALL、LC_MESSAGES、LANG 的顺序推断，都没有的话使用 zh-CN
+   * C 和 POSIX 这样不是语言的值会被跳过
+   */
+  pub fn detect(configured: Option<Locale>) -> Locale {
+    let from_env = |name: &str| env::var(name).ok().and_then(|value| value.parse().ok());
+    from_env("DOCTOR_LOCALE")
+      .or(configured)
+      .or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+          .into_iter()
+          .find_map(from_env)
+      })
+      .unwrap_or(Locale::ZhCn)
+  }
+
+  fn index(self) -> u8 {
+    match self {
+      Locale::ZhCn => 0,
+      Locale::EnUs => 1,
+    }
+  }
+}
+
+impl FromStr for Locale {
+  type Err = crate::Error;
+
+  /* zh、zh-CN、zh_CN.UTF-8 都是中文，en 开头的都是英文 */
+  fn from_str(s: &str) -> crate::Result<Locale> {
+    let language = s
+      .split(['-', '_', '.'])
+      .next()
+      .unwrap_or_default()
+      .to_lowercase();
+    match language.as_str() {
+      "zh" => Ok(Locale::ZhCn),
+      "en" => Ok(Locale::EnUs),
+      _ => Err(ErrorKind::Config(t!("i18n.locale_invalid", s)).into()),
+    }
+  }
+}
+
+/**
+ * 设置输出的语言，没有设置的话第一次输出时使用 Locale::detect 推断
+ */
+pub fn set_locale(locale: Locale) {
+  LOCALE.store(locale.index(), Ordering::Relaxed);
+}
+
+/**
+ * 当前输出的语言
+ */
+pub fn locale() -> Locale {
+  match LOCALE.load(Ordering::Relaxed) {
+    0 => Locale::ZhCn,
+    1 => Locale::EnUs,
+    _ => {
+      let locale = Locale::detect(None);
+      set_locale(locale);
+      locale
+    }
+  }
+}
+
+/// key -> (zh-CN, en-US)，{0}、{1} 会按照顺序替换成参数
+const CATALOG: &[(&str, &str, &str)] = &[
+  // 通用
+  (
+    "i18n.locale_invalid",
+    "不支持 {0} 语言，可选值为 zh-CN 和 en-US",
+    "locale {0} is not supported, expected zh-CN or en-US",
+  ),
+  (
+    "error.log",
+    "设置日志时出错了",
+    "An error occurred setting up the logger.",
+  ),
+  (
+    "error.git",
+    "git 操作出错了",
+    "An error occurred using git.",
+  ),
+  ("error.no_tags", "没有找到 git tag", "No git tags found."),
+  (
+    "error.fs",
+    "读写文件时出错了",
+    "An error occured accessing the disk.",
+  ),
+  (
+    "error.otp",
+    "otp 出错了：{0}",
+    "An error occurred with the one-time password: {0}",
+  ),
+  (
+    "error.npm",
+    "执行 npm 出错了：{0}",
+    "An error occurred running npm: {0}",
+  ),
+  (
+    "error.registry",
+    "请求 npm registry 出错了：{0}",
+    "An error occurred requesting the npm registry: {0}",
+  ),
+  ("error.config", "配置错误：{0}", "Invalid option: {0}"),
+  ("error.other", "未知错误", "Generic error."),
+  // 配置文件
+  (
+    "config.parse_failed",
+    "{0} 解析失败：{1}",
+    "failed to parse {0}: {1}",
+  ),
+  (
+    "config.field_invalid",
+    "{0} 中的 {1} 配置错误：{2}",
+    "{1} in {0} is invalid: {2}",
+  ),
+  ("config.default_source", "配置", "the config"),
+  (
+    "config.packages_empty",
+    "至少需要一个包",
+    "at least one package is required",
+  ),
+  (
+    "config.rule_unknown",
+    "没有 {0} 规则，可选的规则为 {1}",
+    "there is no {0} rule, expected one of {1}",
+  ),
+  // 日志
+  (
+    "log.quiet_and_verbose",
+    "quiet 和 verbose 不能同时使用",
+    "quiet and verbose can not be used together",
+  ),
+  (
+    "log.config_source",
+    "📄 使用 {0} 中的配置",
+    "📄 Using the config in {0}",
+  ),
+  // changelog
+  (
+    "changelog.generating",
+    "-> 正在生成 {0} 的 changelog",
+    "-> Generating the changelog of {0}",
+  ),
+  (
+    "changelog.release_commits",
+    "🔍 {0}：{1} 个 commit",
+    "🔍 {0}: {1} commits",
+  ),
+  (
+    "changelog.commit_excluded",
+    "🔕 {0} 被 {1} 规则过滤",
+    "🔕 {0} is excluded by {1}",
+  ),
+  (
+    "changelog.exclude_commit_invalid",
+    "{0} 不是合法的 commit hash，需要 4 到 40 位十六进制字符",
+    "{0} is not a valid commit hash, expected 4 to 40 hex characters",
+  ),
+  ("changelog.done", "🆗 生成完成。", "🆗 Done."),
+  (
+    "changelog.remove_dir_failed",
+    "删除文件失败 {0}",
+    "Failed to remove {0}",
+  ),
+  (
+    "changelog.create_dir_failed",
+    "创建 changelog 文件夹失败",
+    "Failed to create the changelog directory",
+  ),
+  (
+    "changelog.commit_match_invalid",
+    "commitMatch 不支持 {0}，可选值为 scope、path 和 scope-or-path",
+    "commitMatch does not support {0}, expected scope, path or scope-or-path",
+  ),
+  (
+    "changelog.message_failed",
+    "信息转化失败",
+    "Failed to read the commit message",
+  ),
+  (
+    "changelog.regex_failed",
+    "正则表达式转化失败",
+    "Invalid regular expression",
+  ),
+  (
+    "changelog.commits_failed",
+    "获取commit失败",
+    "Failed to get the commits",
+  ),
+  (
+    "changelog.package_failed",
+    "获取包名失败",
+    "Failed to get the commits of the package",
+  ),
+  (
+    "changelog.generate_failed",
+    "生成changelog 失败，请重试",
+    "Failed to generate the changelog, please retry",
+  ),
+  (
+    "changelog.message_missing",
+    "信息不存在",
+    "The commit has no message",
+  ),
+  (
+    "changelog.author_missing",
+    "author 不存在",
+    "The commit has no author",
+  ),
+  (
+    "changelog.token_missing",
+    "GITHUB_TOKEN 未找到",
+    "GITHUB_TOKEN is not set",
+  ),
+  (
+    "changelog.repo_failed",
+    "json 转化失败，请检查是网络错误，或者 GITHUB_TOKEN 是否失效！",
+    "Failed to parse the repository json, check the network or whether GITHUB_TOKEN has expired!",
+  ),
+  (
+    "changelog.prerelease_invalid",
+    "prerelease 不支持 {0}，可选值为 include、exclude 和 roll-up",
+    "prerelease does not support {0}, expected include, exclude or roll-up",
+  ),
+  (
+    "changelog.latest_invalid",
+    "latest 不支持 {0}，可选值为 stable 和 any",
+    "latest does not support {0}, expected stable or any",
+  ),
+  (
+    "changelog.tag_pattern_version",
+    "tag 的命名方式 {0} 中必须有且只有一个 {version}",
+    "tag pattern {0} must contain {version} exactly once",
+  ),
+  (
+    "changelog.tag_pattern_name",
+    "tag 的命名方式 {0} 中最多只能有一个 {name}",
+    "tag pattern {0} must contain {name} at most once",
+  ),
+  (
+    "changelog.tag_pattern_separate",
+    "tag 的命名方式 {0} 中 {name} 和 {version} 需要分隔开",
+    "tag pattern {0} must separate {name} and {version}",
+  ),
+  (
+    "changelog.timezone_invalid",
+    "timezone 不支持 {0}，可选值为 utc、local、original 或者 +08:00 这样的偏移",
+    "timezone does not support {0}, expected utc, local, original or an offset like +08:00",
+  ),
+  (
+    "changelog.date_format_invalid",
+    "日期格式 {0} 不合法",
+    "date format {0} is not valid",
+  ),
+  (
+    "changelog.format_invalid",
+    "format 不支持 {0}，可选值为 markdown、keep-a-changelog、json、yaml 和 html",
+    "format does not support {0}, expected markdown, keep-a-changelog, json, yaml or html",
+  ),
+  (
+    "changelog.section_invalid",
+    "commit 类型 {0} 不能对应到 {1}，可选值为 {2}",
+    "commit type {0} can not be mapped to {1}, expected one of {2}",
+  ),
+  (
+    "changelog.render_failed",
+    "输出 {0} 失败：{1}",
+    "failed to render {0}: {1}",
+  ),
+  (
+    "changelog.template_invalid",
+    "模板不合法：{0}",
+    "invalid template: {0}",
+  ),
+  (
+    "changelog.template_read_failed",
+    "读取模板 {0} 失败：{1}",
+    "failed to read template {0}: {1}",
+  ),
+  // changelog 中的标题
+  ("changelog.title", "更新日志", "Changelog"),
+  (
+    "changelog.intro",
+    "这个项目所有值得注意的修改都会记录在这个文件中。",
+    "All notable changes to this project will be documented in this file.",
+  ),
+  (
+    "changelog.based_on",
+    "格式基于 [Keep a Changelog](https://keepachangelog.com/zh-CN/1.1.0/)。",
+    "The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).",
+  ),
+  (
+    "changelog.stats",
+    "{0} 个文件修改，{1} 行新增(+)，{2} 行删除(-)",
+    "{0} files changed, {1} insertions(+), {2} deletions(-)",
+  ),
+  (
+    "changelog.jira_pattern_invalid",
+    "jira 的 key 的正则 {0} 不合法",
+    "invalid Jira key pattern {0}",
+  ),
+  (
+    "changelog.jira_url_invalid",
+    "jira 的链接 {0} 中没有 {key}",
+    "the Jira URL {0} has no {key}",
+  ),
+  (
+    "changelog.issue_lookup_failed",
+    "获取 {0} 的信息失败：{1}",
+    "failed to look up {0}: {1}",
+  ),
+  ("changelog.contributor_commit", "1 个提交", "1 commit"),
+  ("changelog.merged_by", "合并者", "merged by"),
+  ("changelog.contributor_commits", "{0} 个提交", "{0} commits"),
+  (
+    "changelog.first_contribution",
+    "首次贡献",
+    "first contribution",
+  ),
+  (
+    "changelog.first_commits_failed",
+    "查找贡献者的第一个 commit 失败：{0}",
+    "failed to find the first commits of contributors: {0}",
+  ),
+  ("section.Added", "新增", "Added"),
+  ("section.Changed", "变更", "Changed"),
+  ("section.Deprecated", "废弃", "Deprecated"),
+  ("section.Removed", "移除", "Removed"),
+  ("section.Fixed", "修复", "Fixed"),
+  ("section.Security", "安全", "Security"),
+  ("section.Reverts", "回滚", "Reverts"),
+  ("section.Contributors", "贡献者", "Contributors"),
+  // npm
+  (
+    "npm.backend_invalid",
+    "backend 不支持 {0}，可选值为 npm 和 registry",
+    "backend does not support {0}, expected npm or registry",
+  ),
+  ("npm.run", "📕 执行 {0}（{1}）", "📕 Running {0} ({1})"),
+  (
+    "npm.run_failed",
+    "😟 {0} 执行失败：{1}",
+    "😟 {0} failed: {1}",
+  ),
+  ("npm.all_done", "😄 全部执行完成", "😄 All done"),
+  (
+    "npm.change_list",
+    "📋 即将执行以下 dist-tag 变更：",
+    "📋 The following dist-tag changes will be made:",
+  ),
+  ("npm.none", "无", "none"),
+  (
+    "npm.change_add",
+    "📦 {0} {1}: {2} -> {3}",
+    "📦 {0} {1}: {2} -> {3}",
+  ),
+  (
+    "npm.change_rm",
+    "📦 {0} {1}: {2} -> 删除",
+    "📦 {0} {1}: {2} -> removed",
+  ),
+  (
+    "npm.publish_failed",
+    "😟 {0} 发布失败！",
+    "😟 {0} failed to publish!",
+  ),
+  (
+    "npm.all_published",
+    "🆗 全部发布成功",
+    "🆗 All packages are published",
+  ),
+  (
+    "npm.wait_for_npm",
+    "😟 发布失败了，等待 npm 恢复再转化为正式版本。",
+    "😟 Publishing failed, wait for npm to recover before tagging the release.",
+  ),
+  (
+    "npm.check_publish",
+    "🔍 检查 {0}@{1} 的发布状态",
+    "🔍 Checking whether {0}@{1} is published",
+  ),
+  (
+    "npm.package_info_failed",
+    "获取包信息失败",
+    "Failed to get the package info",
+  ),
+  (
+    "npm.no_tag",
+    "😟 {0} 没有 {1} 标签，跳过",
+    "😟 {0} has no {1} tag, skipped",
+  ),
+  (
+    "npm.tag_unchanged",
+    "🆗 {0} 的 {1} 已经是 {2}",
+    "🆗 {1} of {0} is already {2}",
+  ),
+  (
+    "npm.remove_latest",
+    "latest 标签不能删除，请使用回滚",
+    "The latest tag can not be removed, roll it back instead",
+  ),
+  (
+    "npm.tag_absent",
+    "🆗 {0} 没有 {1} 标签，跳过",
+    "🆗 {0} has no {1} tag, skipped",
+  ),
+  (
+    "npm.no_previous",
+    "😟 {0} 找不到 {1} 之前的版本，跳过",
+    "😟 {0} has no version before {1}, skipped",
+  ),
+  (
+    "npm.no_changes",
+    "🆗 没有需要变更的 dist-tag",
+    "🆗 No dist-tag needs to change",
+  ),
+  ("npm.confirm", "确认执行吗？(y/N)", "Continue? (y/N)"),
+  ("npm.cancelled", "🔚 已取消", "🔚 Cancelled"),
+  (
+    "npm.no_token",
+    "😟 .npmrc 中没有找到 {0} 的 token，使用 npm 命令",
+    "😟 No token for {0} in .npmrc, using the npm command",
+  ),
+  (
+    "npm.found_packages",
+    "🔍 发现了{0} 个 包 ->",
+    "🔍 Found {0} packages ->",
+  ),
+  (
+    "npm.exec_failed",
+    "执行 {0} 失败：{1}",
+    "failed to run {0}: {1}",
+  ),
+  (
+    "npm.path_failed",
+    "获取 path 失败",
+    "Failed to get the path",
+  ),
+  (
+    "npm.nodejs_missing",
+    "path 中没有找到 nodejs 的目录",
+    "no nodejs directory found in the path",
+  ),
+  // otp
+  (
+    "otp.prompt",
+    "请输入opt,如果没有请留空：",
+    "Enter the otp, leave it empty if there is none:",
+  ),
+  (
+    "otp.expired",
+    "otp 已过期或者不正确",
+    "the otp has expired or is incorrect",
+  ),
+  (
+    "otp.reprompt",
+    "otp 已过期或者不正确，请重新输入：",
+    "The otp has expired or is incorrect, enter it again:",
+  ),
+  (
+    "otp.confirm_required",
+    "非交互模式下需要设置 yes 来确认变更",
+    "yes is required to confirm the changes in non-interactive mode",
+  ),
+  (
+    "otp.invalid",
+    "{0} 不是合法的 otp，应该是 6 到 8 位数字",
+    "{0} is not a valid otp, expected 6 to 8 digits",
+  ),
+  (
+    "otp.secret_invalid",
+    "TOTP 密钥不是合法的 base32",
+    "the TOTP secret is not valid base32",
+  ),
+  (
+    "otp.secret_empty",
+    "TOTP 密钥不能为空",
+    "the TOTP secret can not be empty",
+  ),
+  // workspace
+  (
+    "workspace.parse_failed",
+    "😟 解析 {0} 失败：{1}",
+    "😟 Failed to parse {0}: {1}",
+  ),
+  (
+    "workspace.glob_invalid",
+    "😟 workspace 配置 {0} 不是合法的 glob：{1}",
+    "😟 The workspace pattern {0} is not a valid glob: {1}",
+  ),
+  (
+    "workspace.package_invalid",
+    "😟 格式化 {0} 失败，跳过：{1}",
+    "😟 Failed to parse {0}, skipped: {1}",
+  ),
+  // preflight
+  (
+    "preflight.rule_level_invalid",
+    "规则的级别不支持 {0}，可选值为 off、warning 和 error",
+    "rule level {0} is not supported, expected off, warning or error",
+  ),
+  (
+    "preflight.checking",
+    "🔍 检查 {0}@{1}",
+    "🔍 Checking {0}@{1}",
+  ),
+  (
+    "preflight.version_exists",
+    "{0}@{1} 已经发布过了",
+    "{0}@{1} is already published",
+  ),
+  (
+    "preflight.version_not_bumped",
+    "版本号 {0} 没有比上一个 tag 的版本 {1} 大",
+    "version {0} is not greater than {1} of the last tag",
+  ),
+  (
+    "preflight.missing_entry",
+    "{0} 指向的 {1} 不存在",
+    "{1} referenced by {0} does not exist",
+  ),
+  (
+    "preflight.missing_files",
+    "files 中的 {0} 没有匹配到任何文件",
+    "{0} in files matches no file",
+  ),
+  (
+    "preflight.missing_access",
+    "{0} 是 scope 包，没有配置 publishConfig.access 会按照 restricted 发布",
+    "{0} is a scoped package and will be published as restricted without publishConfig.access",
+  ),
+  (
+    "preflight.dependency_mismatch",
+    "{0} 的版本范围 {1} 不包含当前的版本 {2}",
+    "the range {1} of {0} does not include the current version {2}",
+  ),
+  (
+    "preflight.failed",
+    "😟 有包没有通过检查，请修复之后再发布",
+    "😟 Some packages did not pass the checks, fix them before publishing",
+  ),
+  (
+    "preflight.passed",
+    "🆗 全部检查通过",
+    "🆗 All checks passed",
+  ),
+];
+
+/**
+ * 使用指定的语言翻译，找不到 key 时返回 key 本身
+ */
+pub fn translate(locale: Locale, key: &str, args: &[&dyn Display]) -> String {
+  let text = CATALOG
+    .iter()
+    .find(|(catalog_key, _, _)| *catalog_key == key)
+    .map(|(_, zh_cn, en_us)| match locale {
+      Locale::ZhCn => *zh_cn,
+      Locale::EnUs => *en_us,
+    })
+    .unwrap_or(key);
+  let mut message = text.to_string();
+  for (index, arg) in args.iter().enumerate() {
+    message = message.replace(&format!("{{{}}}", index), &arg.to_string());
+  }
+  message
+}
+
+/**
+ * 使用当前的语言翻译
+ */
+pub fn message(key: &str, args: &[&dyn Display]) -> String {
+  translate(locale(), key, args)
+}
+
+/// t!("npm.run", action, backend) 等同于 i18n::message("npm.run", &[&action, &backend])
+macro_rules! t {
+  ($key:expr) => {
+    $crate::i18n::message($key, &[])
+  };
+  ($key:expr, $($arg:expr),+ $(,)?) => {
+    $crate::i18n::message($key, &[$(&$arg as &dyn std::fmt::Display),+])
+  };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+  use super::{translate, Locale, CATALOG};
+  use std::collections::HashSet;
+
+  /* 文本中用到的参数，{0} -> 0 */
+  fn placeholders(text: &str) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..10)
+      .filter(|index| text.contains(&format!("{{{}}}", index)))
+      .collect();
+    indexes.sort();
+    indexes
+  }
+
+  #[test]
+  fn it_has_a_complete_catalog() {
+    let mut keys = HashSet::new();
+    for (key, zh_cn, en_us) in CATALOG {
+      assert!(keys.insert(key), "duplicate key {}", key);
+      assert_eq!(placeholders(zh_cn), placeholders(en_us), "{}", key);
+    }
+  }
+
+  #[test]
+  fn it_translates_messages() {
+    assert_eq!(
+      translate(
+        Locale::EnUs,
+        "npm.tag_unchanged",
+        &[&"form", &"latest", &"1.0.0"]
+      ),
+      "🆗 latest of form is already 1.0.0"
+    );
+    assert_eq!(
+      translate(
+        Locale::ZhCn,
+        "npm.tag_unchanged",
+        &[&"form", &"latest", &"1.0.0"]
+      ),
+      "🆗 form 的 latest 已经是 1.0.0"
+    );
+    assert_eq!(translate(Locale::EnUs, "no.such.key", &[]), "no.such.key");
+  }
+
+  #[test]
+  fn it_parses_locales() {
+    assert_eq!("zh_CN.UTF-8".parse::<Locale>().unwrap(), Locale::ZhCn);
+    assert_eq!("en-US".parse::<Locale>().unwrap(), Locale::EnUs);
+    assert_eq!("en".parse::<Locale>().unwrap(), Locale::EnUs);
+    assert!("C.UTF-8".parse::<Locale>().is_err());
+    assert!("POSIX".parse::<Locale>().is_err());
+  }
+}
@@ -1,8 +1,13 @@
 #![deny(clippy::all)]
 
 pub mod changelog;
+pub mod config;
 pub mod error;
+pub mod i18n;
+pub mod logger;
 pub mod npm;
+use log::{debug, info};
+use napi::{Env, JsFunction, Ref};
 use napi_derive::napi;
 
 use std::{
@@ -13,7 +18,18 @@ use std::{
 };
 
 pub use crate::error::{Error, ErrorKind, Result};
-use crate::{changelog::Changelogs, npm::Npm};
+use crate::{
+  changelog::{
+    git::{DateOptions, ReleaseOptions, TagPattern, Timezone, WalkOptions},
+    render::OutputFormat,
+    template::Template,
+    Changelogs, CommitMatch, Forge,
+  },
+  config::{ChangelogConfig, Config},
+  i18n::{set_locale, t, Locale},
+  logger::{LogEvent, LogFormat},
+  npm::{otp::OtpOptions, preflight::print_report, BackendKind, Npm},
+};
 
 #[derive(Debug)]
 struct ReadFileError();
@@ -25,10 +41,165 @@ fn create_md_file(package_path: String, content: String) {
   buffer.sync_all().unwrap();
 }
 
+/// changelog 相关操作的配置，没有传入的会使用 doctor.config.json 中 changelog 的配置
+#[napi(object)]
+#[derive(Default)]
+pub struct ChangelogOptions {
+  /// commit 归属到包的方式，scope、path 或者 scope-or-path，默认为 scope
+  pub commit_match: Option<String>,
+  /// 只沿着 merge commit 的第一个 parent 遍历
+  pub first_parent: Option<bool>,
+  /// 跳过 merge commit
+  pub no_merges: Option<bool>,
+  /// 在每个版本中输出修改的文件数、增删的行数和修改最多的文件
+  /// 按照目录匹配时只统计包的目录
+  pub diff_stats: Option<bool>,
+  /// 预发布版本的处理方式，include、exclude 或者 roll-up，默认为 include
+  /// roll-up 会在稳定版本发布之后把预发布版本的修改合并到稳定版本中
+  pub prerelease: Option<String>,
+  /// latest 使用最新的稳定版本（stable）还是最新的任意版本（any），默认为 any
+  pub latest: Option<String>,
+  /// git tag 的命名方式，比如 v{version}、{name}-v{version}，默认为 {name}@{version}
+  pub tag_pattern: Option<String>,
+  /// 版本日期的格式，使用 strftime 的语法，默认为 %Y-%m-%d
+  pub date_format: Option<String>,
+  /// 版本日期的时区，utc、local、original（tag 中记录的时区）或者 +08:00 这样的偏移，默认为 utc
+  pub timezone: Option<String>,
+  /// 在版本的标题下面输出 annotated tag 的注释
+  pub tag_summary: Option<bool>,
+  /// html 格式中在贡献者前面显示 github 的头像
+  pub avatars: Option<bool>,
+  /// 获取 pr 的标题、label 和合并的人，标题会代替 commit 信息输出，label 会用来分组，每个 pr 都会请求一次
+  pub pr_details: Option<bool>,
+  /// 输出的格式，markdown、keep-a-changelog、json、yaml 或者 html，默认为 markdown
+  pub format: Option<String>,
+  /// 自定义的 handlebars 模板文件，相对路径基于仓库的目录，设置之后会忽略 format
+  /// 文件名为 changelog.html.hbs 这样时，生成的文件会使用 html 后缀
+  pub template: Option<String>,
+}
+
+impl ChangelogOptions {
+  /* 没有传入的选项使用配置文件中的值 */
+  fn or_config(self, config: &ChangelogConfig) -> ChangelogOptions {
+    ChangelogOptions {
+      commit_match: self.commit_match.or_else(|| config.commit_match.clone()),
+      first_parent: self.first_parent.or(config.first_parent),
+      no_merges: self.no_merges.or(config.no_merges),
+      diff_stats: self.diff_stats.or(config.diff_stats),
+      prerelease: self.prerelease.or_else(|| config.prerelease.clone()),
+      latest: self.latest.or_else(|| config.latest.clone()),
+      tag_pattern: self.tag_pattern,
+      date_format: self.date_format.or_else(|| config.date_format.clone()),
+      timezone: self.timezone.or_else(|| config.timezone.clone()),
+      tag_summary: self.tag_summary.or(config.tag_summary),
+      avatars: self.avatars.or(config.avatars),
+      pr_details: self.pr_details.or(config.pr_details),
+      format: self.format.or_else(|| config.format.clone()),
+      template: self.template.or_else(|| config.template.clone()),
+    }
+  }
+}
+
+/* 安装日志，读取仓库的配置，并且按照配置设置输出的语言 */
+fn load_config(repo: &str) -> Result<Config> {
+  logger::install()?;
+  let config = Config::load(repo)?;
+  set_locale(Locale::detect(config.locale()?));
+  if let Some(source) = &config.source {
+    debug!("{}", t!("log.config_source", source));
+  }
+  Ok(config)
+}
+
+fn create_changelogs(
+  repo: String,
+  config: &Config,
+  options: Option<ChangelogOptions>,
+) -> Result<Changelogs> {
+  let mut options = options.unwrap_or_default().or_config(&config.changelog);
+  options.tag_pattern = options.tag_pattern.or_else(|| config.tag_pattern.clone());
+  let commit_match = match options.commit_match {
+    Some(commit_match) => commit_match.parse()?,
+    None => CommitMatch::Scope,
+  };
+  let walk_options = WalkOptions {
+    first_parent: options.first_parent.unwrap_or(false),
+    no_merges: options.no_merges.unwrap_or(false),
+    diff_stats: options.diff_stats.unwrap_or(false),
+    ..WalkOptions::default()
+  };
+  let format = match options.format {
+    Some(format) => format.parse()?,
+    None => OutputFormat::Markdown,
+  };
+  let mut release_options = ReleaseOptions::default();
+  if let Some(prerelease) = options.prerelease {
+    release_options.prerelease = prerelease.parse()?;
+  }
+  if let Some(latest) = options.latest {
+    release_options.latest = latest.parse()?;
+  }
+  if let Some(tag_pattern) = options.tag_pattern {
+    release_options.tag_pattern = tag_pattern.parse()?;
+  }
+  if options.date_format.is_some() || options.timezone.is_some() {
+    let timezone = match options.timezone {
+      Some(timezone) => timezone.parse()?,
+      None => Timezone::Utc,
+    };
+    let date_format = options
+      .date_format
+      .unwrap_or_else(|| DateOptions::DEFAULT_FORMAT.to_string());
+    release_options.date = DateOptions::new(&date_format, timezone)?;
+  }
+  let template = match options.template {
+    Some(template) => Some(Template::from_file(
+      &Path::new(&repo).join(template).display().to_string(),
+    )?),
+    None => None,
+  };
+  let forge = Forge {
+    html_url: config.forge.url.clone(),
+    api_url: config.forge.api_url.clone(),
+    repo_name: config.forge.repo.clone(),
+  };
+  let mut changelogs = Changelogs::new_with_forge(repo, forge)
+    .with_commit_match(commit_match)
+    .with_walk_options(walk_options)
+    .with_release_options(release_options)
+    .with_tag_summary(options.tag_summary.unwrap_or(false))
+    .with_avatars(options.avatars.unwrap_or(false))
+    .with_pr_details(options.pr_details.unwrap_or(false))
+    .with_format(format)
+    .with_commit_types(config.commit_types()?)
+    .with_filters(config.changelog.exclude.filters());
+  if let Some(jira) = &config.changelog.jira {
+    changelogs = changelogs.with_jira(jira.keys()?);
+  }
+  if let Some(package_prefix) = &config.package_prefix {
+    changelogs = changelogs.with_package_prefix(package_prefix.clone());
+  }
+  if let Some(packages) = &config.packages {
+    changelogs = changelogs.with_package_list(packages.clone());
+  }
+  if let Some(packages_dir) = &config.packages_dir {
+    changelogs = changelogs.with_packages_dir(packages_dir.clone());
+  }
+  Ok(match template {
+    Some(template) => changelogs.with_template(template),
+    None => changelogs,
+  })
+}
+
 #[napi]
-pub fn gen_changelogs(repo: String, changelog_path: Option<String>) {
+pub fn gen_changelogs(
+  repo: String,
+  changelog_path: Option<String>,
+  options: Option<ChangelogOptions>,
+) -> napi::Result<()> {
+  let config = to_napi_result(load_config(&repo))?;
   let mut repo_changelog_path = PathBuf::new();
-  let changelog_path = match changelog_path {
+  let changelog_path = match changelog_path.or_else(|| config.changelog.output.clone()) {
     Some(p) => p,
     None => ".changelogs".to_string(),
   };
@@ -39,32 +210,39 @@ pub fn gen_changelogs(repo: String, changelog_path: Option<String>) {
   let dir_path = repo_changelog_path.display().to_string();
   if Path::new(&dir_path).exists() {
     fs::remove_dir_all(repo_changelog_path.display().to_string())
-      .expect(format!("删除文件失败 {dir_path}", dir_path = dir_path).as_str());
+      .unwrap_or_else(|_| panic!("{}", t!("changelog.remove_dir_failed", dir_path)));
   }
-  create_dir(dir_path).expect("创建 changelog 文件夹失败");
+  create_dir(dir_path).unwrap_or_else(|_| panic!("{}", t!("changelog.create_dir_failed")));
 
   // 只写入 latest
+  let mut changelogs = to_napi_result(create_changelogs(repo, &config, options))?;
   let mut md_path = repo_changelog_path.clone();
 
-  md_path.push("components.md");
+  md_path.push(format!("components.{}", changelogs.extension()));
 
-  let mut md_str_list: Vec<String> = vec![];
-
-  let md_file_content_list = Changelogs::new(repo).get_change_log_list();
-  for md_file_content in md_file_content_list {
-    println!("-> 正在生成 {} 的 changelog", md_file_content.package);
-    md_str_list.push(md_file_content.content);
+  let package_notes_list = changelogs.get_change_log_list();
+  for package_notes in &package_notes_list {
+    info!("{}", t!("changelog.generating", package_notes.package));
   }
 
-  create_md_file(md_path.display().to_string(), md_str_list.join(""));
+  create_md_file(
+    md_path.display().to_string(),
+    to_napi_result(changelogs.render(&package_notes_list))?,
+  );
 
-  println!("{:?}", "🆗 生成完成。");
+  info!("{}", t!("changelog.done"));
+  Ok(())
 }
 
 #[napi]
-pub fn gen_all_changelogs(repo: String, changelog_path: Option<String>) {
+pub fn gen_all_changelogs(
+  repo: String,
+  changelog_path: Option<String>,
+  options: Option<ChangelogOptions>,
+) -> napi::Result<()> {
+  let config = to_napi_result(load_config(&repo))?;
   let mut repo_changelog_path = PathBuf::new();
-  let changelog_path = match changelog_path {
+  let changelog_path = match changelog_path.or_else(|| config.changelog.output.clone()) {
     Some(p) => p,
     None => ".changelogs".to_string(),
   };
@@ -74,29 +252,248 @@ pub fn gen_all_changelogs(repo: String, changelog_path: Option<String>) {
   let dir_path = repo_changelog_path.display().to_string();
   if Path::new(&dir_path).exists() {
     fs::remove_dir_all(repo_changelog_path.display().to_string())
-      .expect(format!("删除文件失败 {dir_path}", dir_path = dir_path).as_str());
+      .unwrap_or_else(|_| panic!("{}", t!("changelog.remove_dir_failed", dir_path)));
   }
-  create_dir(dir_path).expect("创建 changelog 文件夹失败");
+  create_dir(dir_path).unwrap_or_else(|_| panic!("{}", t!("changelog.create_dir_failed")));
 
   // 只写入 latest
-  let md_file_content_list = Changelogs::new(repo).get_all_change_log_list();
+  let mut changelogs = to_napi_result(create_changelogs(repo, &config, options))?;
+  let package_notes_list = changelogs.get_all_change_log_list();
   let mut md_path = repo_changelog_path.clone();
-  md_path.push("components.md");
-  let mut md_str_list: Vec<String> = vec![];
+  md_path.push(format!("components.{}", changelogs.extension()));
 
-  for md_file_content in md_file_content_list {
-    md_str_list.push(md_file_content.content.to_string());
-    println!("-> 正在生成 {} 的 changelog", md_file_content.package);
+  for package_notes in &package_notes_list {
+    info!("{}", t!("changelog.generating", package_notes.package));
   }
 
-  create_md_file(md_path.display().to_string(), md_str_list.join(""));
+  create_md_file(
+    md_path.display().to_string(),
+    to_napi_result(changelogs.render(&package_notes_list))?,
+  );
 
-  println!("{:?}", "🆗 生成完成。");
+  info!("{}", t!("changelog.done"));
+  Ok(())
 }
 
+/// 生成 head 到最新的 tag 之间还没有发布的 changelog，返回配置的格式，默认为 markdown，可以用于发布 PR 的描述
+/// head 默认为 HEAD，也可以是任意的分支名、tag 或者 commit
 #[napi]
-pub fn check_publish(repo: String) {
-  Npm::new(repo).check();
+pub fn gen_unreleased_changelog(
+  repo: String,
+  head: Option<String>,
+  options: Option<ChangelogOptions>,
+) -> napi::Result<String> {
+  let head = head.unwrap_or_else(|| "HEAD".to_string());
+  let config = to_napi_result(load_config(&repo))?;
+  let mut changelogs = to_napi_result(create_changelogs(repo, &config, options))?;
+  let package_notes_list = to_napi_result(changelogs.get_unreleased_change_log_list(&head))?;
+
+  for package_notes in &package_notes_list {
+    info!("{}", t!("changelog.generating", package_notes.package));
+  }
+
+  info!("{}", t!("changelog.done"));
+  to_napi_result(changelogs.render(&package_notes_list))
+}
+
+/// 生成两个 ref 之间的 changelog，返回配置的格式，默认为 markdown，ref 可以是分支名、tag 或者 commit
+/// to 默认为 HEAD，标题中会带上 github 的 compare 链接
+#[napi]
+pub fn gen_range_changelog(
+  repo: String,
+  from: String,
+  to: Option<String>,
+  options: Option<ChangelogOptions>,
+) -> napi::Result<String> {
+  let to = to.unwrap_or_else(|| "HEAD".to_string());
+  let config = to_napi_result(load_config(&repo))?;
+  let mut changelogs = to_napi_result(create_changelogs(repo, &config, options))?;
+  let package_notes_list = to_napi_result(changelogs.get_ref_range_change_log_list(&from, &to))?;
+
+  for package_notes in &package_notes_list {
+    info!("{}", t!("changelog.generating", package_notes.package));
+  }
+
+  info!("{}", t!("changelog.done"));
+  to_napi_result(changelogs.render(&package_notes_list))
+}
+
+/// dist-tag 相关操作的配置，没有传入的会从配置文件和环境变量读取
+#[napi(object)]
+pub struct NpmOptions {
+  /// 一次性密码，等同于 NPM_CONFIG_OTP
+  pub otp: Option<String>,
+  /// base32 格式的 TOTP 密钥，用来自动生成 otp
+  pub totp_secret: Option<String>,
+  /// 非交互模式，不会读取 stdin，CI 环境下默认开启
+  pub non_interactive: Option<bool>,
+  /// 跳过 dist-tag 变更的确认
+  pub yes: Option<bool>,
+  /// dist-tag 的执行方式，npm 或者 registry，默认为 npm
+  pub backend: Option<String>,
+  /// git tag 的命名方式，比如 v{version}、{name}-v{version}，默认为 {name}@{version}
+  pub tag_pattern: Option<String>,
+}
+
+impl From<Option<NpmOptions>> for OtpOptions {
+  fn from(options: Option<NpmOptions>) -> OtpOptions {
+    match options {
+      Some(options) => OtpOptions {
+        otp: options.otp,
+        totp_secret: options.totp_secret,
+        non_interactive: options.non_interactive.unwrap_or(false),
+        yes: options.yes.unwrap_or(false),
+      },
+      None => OtpOptions::default(),
+    }
+  }
+}
+
+fn create_npm(repo: String, options: Option<NpmOptions>) -> Result<Npm> {
+  let config = load_config(&repo)?;
+  let backend = match options
+    .as_ref()
+    .and_then(|options| options.backend.as_deref())
+    .or(config.npm.backend.as_deref())
+  {
+    Some(backend) => backend.parse()?,
+    None => BackendKind::Npm,
+  };
+  let tag_pattern = match options
+    .as_ref()
+    .and_then(|options| options.tag_pattern.as_deref())
+    .or(config.tag_pattern.as_deref())
+  {
+    Some(tag_pattern) => tag_pattern.parse()?,
+    None => TagPattern::default(),
+  };
+  let mut npm = Npm::new(repo);
+  // registry 要在 backend 之前设置，registry 的 backend 会使用它
+  if let Some(registry) = &config.npm.registry {
+    npm = npm.with_registry(registry);
+  }
+  Ok(
+    npm
+      .with_otp_options(options.into())
+      .with_backend(backend)
+      .with_tag_pattern(tag_pattern)
+      .with_rule_levels(config.rule_levels()?.into_iter().collect()),
+  )
+}
+
+fn to_napi_result<T>(result: Result<T>) -> napi::Result<T> {
+  result.map_err(|err| napi::Error::from_reason(err.to_string()))
+}
+
+#[napi]
+pub fn check_publish(
+  repo: String,
+  tag: Option<String>,
+  options: Option<NpmOptions>,
+) -> napi::Result<()> {
+  let tag = tag.unwrap_or_else(|| "latest".to_string());
+  to_napi_result(create_npm(repo, options).and_then(|npm| npm.check(&tag)))
+}
+
+#[napi]
+pub fn promote_dist_tag(
+  repo: String,
+  from: String,
+  to: Option<String>,
+  options: Option<NpmOptions>,
+) -> napi::Result<()> {
+  let to = to.unwrap_or_else(|| "latest".to_string());
+  to_napi_result(create_npm(repo, options).and_then(|npm| npm.promote(&from, &to)))
+}
+
+#[napi]
+pub fn remove_dist_tag(repo: String, tag: String, options: Option<NpmOptions>) -> napi::Result<()> {
+  to_napi_result(create_npm(repo, options).and_then(|npm| npm.remove_tag(&tag)))
+}
+
+#[napi]
+pub fn rollback_latest(repo: String, options: Option<NpmOptions>) -> napi::Result<()> {
+  to_napi_result(create_npm(repo, options).and_then(|npm| npm.rollback_latest()))
+}
+
+/// 发布之前检查所有的包，返回结构化的检查结果
+#[napi]
+pub fn preflight(repo: String) -> napi::Result<serde_json::Value> {
+  let report = to_napi_result(create_npm(repo, None))?.preflight();
+  print_report(&report);
+  serde_json::to_value(&report).map_err(|err| napi::Error::from_reason(err.to_string()))
+}
+
+/// 日志的配置
+#[napi(object)]
+#[derive(Default)]
+pub struct LogOptions {
+  /// 只输出警告和错误
+  pub quiet: Option<bool>,
+  /// 输出调试信息，比如使用的配置文件和每个版本的 commit 数量
+  pub verbose: Option<bool>,
+  /// 每条日志输出为一行 json，包含 level、target、message 和 timestamp
+  pub json_logs: Option<bool>,
+}
+
+/* js 的回调，释放时删除引用 */
+struct JsListener {
+  env: Env,
+  callback: Ref<()>,
+}
+
+impl JsListener {
+  fn call(&self, event: &LogEvent) -> napi::Result<()> {
+    let callback: JsFunction = self.env.get_reference_value(&self.callback)?;
+    let event = self.env.to_js_value(event)?;
+    callback.call(None, &[event])?;
+    Ok(())
+  }
+}
+
+impl Drop for JsListener {
+  fn drop(&mut self) {
+    let _ = self.callback.unref(self.env);
+  }
+}
+
+/// 设置日志的级别和格式，对之后所有的调用生效
+/// 传入 callback 之后日志不再输出到 stderr，而是同步调用 callback，参数为 { level, target, message, timestamp }
+/// 不传入 callback 时恢复输出到 stderr
+#[napi]
+pub fn set_logger(
+  mut env: Env,
+  options: Option<LogOptions>,
+  callback: Option<JsFunction>,
+) -> napi::Result<()> {
+  let options = options.unwrap_or_default();
+  let level = to_napi_result(logger::level(
+    options.quiet.unwrap_or(false),
+    options.verbose.unwrap_or(false),
+  ))?;
+  let format = if options.json_logs.unwrap_or(false) {
+    LogFormat::Json
+  } else {
+    LogFormat::Text
+  };
+  to_napi_result(logger::configure(level, format))?;
+
+  let listener: Option<logger::Listener> = match callback {
+    Some(callback) => {
+      let listener = JsListener {
+        env,
+        callback: env.create_reference(callback)?,
+      };
+      // node 退出之前删除引用，不能等到线程销毁时 env 已经不存在了
+      env.add_env_cleanup_hook((), |_| logger::set_listener(None))?;
+      Some(Box::new(move |event: &LogEvent| {
+        let _ = listener.call(event);
+      }))
+    }
+    None => None,
+  };
+  logger::set_listener(listener);
+  Ok(())
 }
 
 #[cfg(test)]
@@ -112,7 +509,9 @@ mod tests {
       gen_changelogs(
         "/Users/shuaichen/Documents/github/pro-components".to_string(),
         Some(".changhelog2".to_string()),
-      );
+        None,
+      )
+      .unwrap();
     }
     assert_eq!(2 + 2, 4);
   }
@@ -123,7 +522,9 @@ mod tests {
       gen_all_changelogs(
         "/Users/shuaichen/Documents/github/pro-components".to_string(),
         None,
-      );
+        None,
+      )
+      .unwrap();
     }
     assert_eq!(2 + 2, 4);
   }
@@ -131,10 +532,15 @@ mod tests {
   #[test]
   fn it_check_publish() {
     if Path::new("/Users/shuaichen/Documents/github/pro-components").exists() {
-      check_publish("/Users/shuaichen/Documents/github/pro-components".to_string());
+      check_publish(
+        "/Users/shuaichen/Documents/github/pro-components".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
     }
     if Path::new("C:/github/pro-components").exists() {
-      check_publish("C:/github/pro-components".to_string());
+      check_publish("C:/github/pro-components".to_string(), None, None).unwrap();
     }
     assert_eq!(2 + 2, 4);
   }
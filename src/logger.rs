@@ -0,0 +1,211 @@
+use chrono::{SecondsFormat, Utc};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// 日志的格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+  /// 只输出日志的内容
+  #[default]
+  Text,
+  /// 每条日志输出为一行 json
+  Json,
+}
+
+/// 一条日志，json 格式的日志和 js 的回调都使用这个结构
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LogEvent {
+  /// error、warn、info、debug 或者 trace
+  pub level: String,
+  /// 输出日志的模块，比如 doctor::npm
+  pub target: String,
+  pub message: String,
+  /// RFC 3339 格式的时间
+  pub timestamp: String,
+}
+
+/// 接收日志的回调，设置之后日志不再输出到 stderr
+pub type Listener = Box<dyn Fn(&LogEvent)>;
+
+thread_local! {
+  /* js 的回调只能在主线程调用，所以回调保存在线程中，其他线程的日志还是输出到 stderr */
+  static LISTENER: RefCell<Option<Listener>> = RefCell::new(None);
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static JSON: AtomicBool = AtomicBool::new(false);
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= log::max_level()
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+    let event = LogEvent {
+      level: record.level().as_str().to_lowercase(),
+      target: record.target().to_string(),
+      message: record.args().to_string(),
+      timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+    };
+    // 回调中再输出日志时回调已经被借用了，这时输出到 stderr
+    // stdout 留给输出的 changelog，重定向到文件时不会混入日志
+    let handled = LISTENER.with(|listener| match listener.try_borrow() {
+      Ok(listener) => listener.as_ref().map(|listener| listener(&event)).is_some(),
+      Err(_) => false,
+    });
+    if handled {
+      return;
+    }
+    if JSON.load(Ordering::Relaxed) {
+      if let Ok(line) = serde_json::to_string(&event) {
+        eprintln!("{}", line);
+      }
+    } else {
+      eprintln!("{}", event.message);
+    }
+  }
+
+  fn flush(&self) {
+    let _ = io::stderr().flush();
+  }
+}
+
+/**
+ * 安装日志，默认输出 info 及以上的日志，重复调用不会修改已有的配置
+ * 已经安装了其他的日志时返回 ErrorKind::Log
+ */
+pub fn install() -> crate::Result<()> {
+  if INSTALLED.swap(true, Ordering::SeqCst) {
+    return Ok(());
+  }
+  if log::set_logger(&LOGGER).is_err() {
+    INSTALLED.store(false, Ordering::SeqCst);
+    return Err(ErrorKind::Log.into());
+  }
+  log::set_max_level(LevelFilter::Info);
+  Ok(())
+}
+
+/**
+ * 设置日志的级别和格式
+ */
+pub fn configure(level: LevelFilter, format: LogFormat) -> crate::Result<()> {
+  install()?;
+  log::set_max_level(level);
+  JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+  Ok(())
+}
+
+/**
+ * 当前日志的格式
+ */
+pub fn format() -> LogFormat {
+  if JSON.load(Ordering::Relaxed) {
+    LogFormat::Json
+  } else {
+    LogFormat::Text
+  }
+}
+
+/**
+ * quiet 只输出警告和错误，verbose 会输出调试信息，不能同时使用
+ */
+pub fn level(quiet: bool, verbose: bool) -> crate::Result<LevelFilter> {
+  match (quiet, verbose) {
+    (true, true) => Err(ErrorKind::Config(t!("log.quiet_and_verbose")).into()),
+    (true, false) => Ok(LevelFilter::Warn),
+    (false, true) => Ok(LevelFilter::Debug),
+    (false, false) => Ok(LevelFilter::Info),
+  }
+}
+
+/**
+ * 设置当前线程接收日志的回调，传入 None 时恢复输出到 stderr
+ */
+pub fn set_listener(listener: Option<Listener>) {
+  LISTENER.with(|current| *current.borrow_mut() = listener);
+}
+
+#[cfg(test)]
+mod tests {
+  use log::LevelFilter;
+  use std::cell::RefCell;
+  use std::env;
+  use std::process::Command;
+  use std::rc::Rc;
+
+  use super::{configure, level, set_listener, LogFormat};
+
+  /* 在子进程中运行，输出 changelog 和日志 */
+  #[test]
+  fn it_prints_logs_and_output() {
+    if env::var_os("DOCTOR_LOGGER_CHILD").is_none() {
+      return;
+    }
+    configure(LevelFilter::Info, LogFormat::Json).unwrap();
+    log::info!("📦 form");
+    println!("## @ant-design/pro-form@1.0.0");
+    log::logger().flush();
+  }
+
+  #[test]
+  fn it_logs_to_stderr() {
+    let output = Command::new(env::current_exe().unwrap())
+      .args([
+        "logger::tests::it_prints_logs_and_output",
+        "--exact",
+        "--nocapture",
+      ])
+      .env("DOCTOR_LOGGER_CHILD", "1")
+      .output()
+      .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stdout.contains("## @ant-design/pro-form@1.0.0\n"));
+    assert!(!stdout.contains("📦 form"));
+    assert!(stderr.contains(r#""message":"📦 form""#));
+    assert!(!stderr.contains("@ant-design/pro-form@1.0.0"));
+  }
+
+  #[test]
+  fn it_sends_events_to_the_listener() {
+    configure(LevelFilter::Info, LogFormat::Text).unwrap();
+    let events = Rc::new(RefCell::new(vec![]));
+    let received = events.clone();
+    set_listener(Some(Box::new(move |event| {
+      received.borrow_mut().push(event.clone())
+    })));
+    log::info!("🆗 {}", "done");
+    log::debug!("hidden");
+    set_listener(None);
+    log::info!("not received");
+
+    let events = events.borrow();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].level, "info");
+    assert_eq!(events[0].target, module_path!());
+    assert_eq!(events[0].message, "🆗 done");
+  }
+
+  #[test]
+  fn it_maps_modes_to_levels() {
+    assert_eq!(level(false, false).unwrap(), LevelFilter::Info);
+    assert_eq!(level(true, false).unwrap(), LevelFilter::Warn);
+    assert_eq!(level(false, true).unwrap(), LevelFilter::Debug);
+    assert!(level(true, true).is_err());
+  }
+}
@@ -1,136 +1,244 @@
-﻿use git2::Repository;
+﻿pub mod backend;
+pub mod otp;
+pub mod preflight;
+pub mod registry;
+pub mod workspace;
+use git2::Repository;
+use log::{debug, info, warn};
 use reqwest::Client;
 use semver::Version;
 use serde::Deserialize;
-use serde_json;
-use std::env::consts::OS;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, env, fs, io, process::Command};
-
-use crate::changelog::git::get_version;
-
-#[cfg(windows)]
-pub const NPM: &'static str = "npm.cmd";
-
-#[cfg(not(windows))]
-pub const NPM: &'static str = "npm";
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use self::backend::{DistTagBackend, NpmCliBackend};
+use self::otp::{OtpOptions, OtpProvider};
+use self::preflight::RuleLevel;
+use self::registry::{NpmRc, RegistryBackend};
+use self::workspace::find_packages;
+use crate::changelog::git::TagPattern;
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// otp 过期之后最多重试的次数
+const MAX_OTP_RETRY: usize = 3;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct NpmPackageInfo {
   name: String,
   version: String,
+  #[serde(default)]
+  private: bool,
+  /// 包所在的目录
+  #[serde(skip)]
+  path: String,
+}
+
+impl NpmPackageInfo {
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /* 包所在的目录 */
+  pub fn path(&self) -> &str {
+    &self.path
+  }
 }
 
 pub struct Npm {
   client: Client,
   path: String,
+  registry: String,
   package_list: Vec<NpmPackageInfo>,
+  otp_options: OtpOptions,
+  backend: Box<dyn DistTagBackend>,
+  /// git tag 的命名方式，默认为 {name}@{version}
+  tag_pattern: TagPattern,
+  /// 发布前检查的规则级别，没有配置的规则使用默认的级别
+  rule_levels: HashMap<String, RuleLevel>,
 }
 
-async fn run_dist_tag(
-  package_version: NpmPackageInfo,
-  opt: Arc<Mutex<String>>,
-  npm_path: Arc<Mutex<String>>,
-) -> String {
-  println!(
-    "📕 执行 npm dist-tag add {} latest",
-    format!(
-      "{name}@{version}",
-      name = package_version.name,
-      version = package_version.version
-    )
-  );
-  let output = Command::new(NPM)
-    .env("NPM_CONFIG_OTP", opt.lock().unwrap().clone())
-    .current_dir(npm_path.lock().unwrap().clone())
-    .arg("dist-tag")
-    .arg("add")
-    .arg(format!(
-      "{name}@{version}",
-      name = package_version.name,
-      version = package_version.version
-    ))
-    .arg("latest")
-    .spawn()
-    .expect("执行异常，提示")
-    .wait_with_output()
-    .unwrap();
+/// 使用哪种方式执行 dist-tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+  /// 调用 npm 命令
+  Npm,
+  /// 使用 .npmrc 中的 token 直接请求 registry，没有 token 时退回到 npm 命令
+  Registry,
+}
 
-  let output_string = String::from_utf8_lossy(&output.stderr);
+impl FromStr for BackendKind {
+  type Err = crate::Error;
 
-  if !output_string.is_empty() {
-    println!(
-      "{}",
-      output_string.split("\n").collect::<Vec<&str>>().join("\n")
-    );
+  fn from_str(s: &str) -> crate::Result<BackendKind> {
+    match s {
+      "npm" => Ok(BackendKind::Npm),
+      "registry" => Ok(BackendKind::Registry),
+      _ => Err(ErrorKind::Config(t!("npm.backend_invalid", s)).into()),
+    }
   }
-  let output_string = String::from_utf8_lossy(&output.stdout).to_string();
+}
 
-  if !output_string.is_empty() {
-    println!(
-      "{}",
-      output_string.split("\n").collect::<Vec<&str>>().join("\n")
-    );
+/// 一次 dist-tag 操作
+#[derive(Debug, Clone)]
+pub enum DistTagAction {
+  /// npm dist-tag add <name>@<version> <tag>
+  Add {
+    name: String,
+    version: String,
+    tag: String,
+  },
+  /// npm dist-tag rm <name> <tag>
+  Rm { name: String, tag: String },
+}
+
+impl Display for DistTagAction {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DistTagAction::Add { name, version, tag } => {
+        write!(f, "dist-tag add {name}@{version} {tag}")
+      }
+      DistTagAction::Rm { name, tag } => write!(f, "dist-tag rm {name} {tag}"),
+    }
   }
-  output_string
 }
 
-async fn gen_package_version_list(
-  package_list: Vec<NpmPackageInfo>,
-  input: String,
-  npm_path: String,
-) {
-  let input = Arc::new(Mutex::new(input));
-  let npm_path = Arc::new(Mutex::new(npm_path));
+/// 待确认的 dist-tag 变更，current 是 registry 上这个 tag 现在指向的版本
+#[derive(Debug, Clone)]
+pub struct DistTagChange {
+  pub action: DistTagAction,
+  pub current: Option<String>,
+}
+
+impl Display for DistTagChange {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let none = t!("npm.none");
+    let current = self.current.as_deref().unwrap_or(&none);
+    match &self.action {
+      DistTagAction::Add { name, version, tag } => {
+        write!(f, "{}", t!("npm.change_add", name, tag, current, version))
+      }
+      DistTagAction::Rm { name, tag } => write!(f, "{}", t!("npm.change_rm", name, tag, current)),
+    }
+  }
+}
 
-  let tasks: Vec<_> = package_list
+/* 依次执行 dist-tag，otp 过期的话重新获取之后再试 */
+fn run_dist_tag_list(
+  action_list: Vec<DistTagAction>,
+  backend: &dyn DistTagBackend,
+  otp_provider: &mut OtpProvider,
+) -> crate::Result<()> {
+  let mut failed_list: Vec<String> = vec![];
+  for action in action_list {
+    info!("{}", t!("npm.run", action, backend.name()));
+    let mut retry = 0;
+    loop {
+      let otp = otp_provider.get()?;
+      match backend.run(&action, otp.as_deref()) {
+        Ok(()) => break,
+        Err(err) => {
+          if let ErrorKind::Otp(_) = err.kind() {
+            if retry < MAX_OTP_RETRY {
+              retry += 1;
+              otp_provider.refresh()?;
+              continue;
+            }
+          }
+          warn!("{}", t!("npm.run_failed", action, err));
+          failed_list.push(action.to_string());
+          break;
+        }
+      }
+    }
+  }
+  if !failed_list.is_empty() {
+    return Err(ErrorKind::Npm(failed_list.join("\n")).into());
+  }
+  info!("{}", t!("npm.all_done"));
+  Ok(())
+}
+
+/* 把 from 指向的版本提升为 to，to 已经指向这个版本时不需要修改 */
+fn promote_change(
+  name: &str,
+  dist_tags: &HashMap<String, String>,
+  from: &str,
+  to: &str,
+) -> Option<DistTagChange> {
+  let version = match dist_tags.get(from) {
+    Some(version) => version.clone(),
+    None => {
+      warn!("{}", t!("npm.no_tag", name, from));
+      return None;
+    }
+  };
+  let current = dist_tags.get(to).cloned();
+  if current.as_deref() == Some(version.as_str()) {
+    info!("{}", t!("npm.tag_unchanged", name, to, version));
+    return None;
+  }
+  Some(DistTagChange {
+    action: DistTagAction::Add {
+      name: name.to_string(),
+      version,
+      tag: to.to_string(),
+    },
+    current,
+  })
+}
+
+/* 比 current 小的最新的正式版本，versions 按照版本号从大到小排序 */
+fn previous_stable(
+  current: &Version,
+  versions: impl IntoIterator<Item = Version>,
+) -> Option<Version> {
+  versions
     .into_iter()
-    .map(|package_version| async {
-      let input = input.clone();
-      let npm_path = npm_path.clone();
-      let out_string = run_dist_tag(package_version, input, npm_path).await;
-      out_string
-    })
-    .collect();
-
-  let mut out_string_list: Vec<String> = vec![];
-  for task in tasks {
-    out_string_list.push(task.await);
-  }
-  println!(
-    "😄 全部执行完成：{}",
-    out_string_list.join("\n").to_string().trim()
-  );
+    .find(|version| version.pre.is_empty() && version < current)
+}
+
+fn print_change_list(change_list: &[DistTagChange]) {
+  info!("{}", t!("npm.change_list"));
+  info!("-------------------");
+  for change in change_list {
+    info!("{}", change);
+  }
+  info!("-------------------");
 }
 
 impl Npm {
-  /* 如果有发布失败的包，那么就不执行 npm dist-tag add latest */
-  #[tokio::main]
-  pub async fn check(&self) {
+  /* 如果有发布失败的包，那么就不执行 npm dist-tag add <tag> */
+  pub fn check(&self, tag: &str) -> crate::Result<()> {
     let map = self.check_package_list_publish_success();
 
     let all_published = map.iter().any(|(package, published)| -> bool {
       if published.to_owned().to_owned() {
         return true;
       }
-      println!("😟 {} 发布失败！", package);
+      warn!("{}", t!("npm.publish_failed", package));
       false
     });
 
     if all_published {
-      println!("🆗 全部发布成功");
-      let npm_path = self.get_path();
-
-      // 读取 opt
-      println!("请输入opt,如果没有请留空：");
-      let mut input = String::new();
-      io::stdin().read_line(&mut input).expect("读取失败");
-      let package_list = self.package_list.clone();
-      gen_package_version_list(package_list, input, npm_path).await;
+      info!("{}", t!("npm.all_published"));
+
+      let mut otp_provider = OtpProvider::new(self.otp_options.clone());
+      let action_list = self
+        .package_list
+        .iter()
+        .map(|package| DistTagAction::Add {
+          name: package.name.clone(),
+          version: package.version.clone(),
+          tag: tag.to_string(),
+        })
+        .collect();
+      run_dist_tag_list(action_list, self.backend.as_ref(), &mut otp_provider)?;
     } else {
-      println!("😟 发布失败了，等待 npm 恢复再转化为正式版本。");
+      warn!("{}", t!("npm.wait_for_npm"));
     }
+    Ok(())
   }
   /* 判断这个包是不是发布成功了 */
   pub fn check_package_list_publish_success(&self) -> HashMap<String, bool> {
@@ -147,12 +255,13 @@ impl Npm {
    */
   pub fn check_publish_success(&self, name: &str, version: &str) -> bool {
     let endpoint = format!(
-      "https://registry.npmjs.org/{name}/{version}",
+      "{registry}{name}/{version}",
+      registry = self.registry,
       name = name,
       version = version
     );
 
-    println!("🔍 检查 {}@{} 的发布状态", name, version);
+    info!("{}", t!("npm.check_publish", name, version));
 
     let json = self
       .client
@@ -160,81 +269,211 @@ impl Npm {
       .send()
       .unwrap()
       .json::<NpmPackageInfo>()
-      .expect("获取包信息失败");
+      .unwrap_or_else(|_| panic!("{}", t!("npm.package_info_failed")));
 
-    println!("{:?}", json);
+    debug!("{:?}", json);
     json.version == version
   }
 
   /**
    * 获取  latest 的最后一个版本
    */
-  pub fn get_package_latest_version(&self, name: &str) -> String {
-    let endpoint = format!("https://registry.npmjs.org/{name}/latest", name = name,);
+  pub fn get_package_latest_version(&self, name: &str) -> crate::Result<String> {
+    let endpoint = format!(
+      "{registry}{name}/latest",
+      registry = self.registry,
+      name = name
+    );
 
-    self
+    let json = self
       .client
       .get(&endpoint)
       .send()
-      .unwrap()
+      .map_err(|err| ErrorKind::Registry(err.to_string()))?
       .json::<NpmPackageInfo>()
-      .unwrap()
-      .version
-  }
-
-  /* 获取 nodejs 的安装路径 */
-  fn get_path(&self) -> String {
-    if OS == "windows" {
-      return env::var("path")
-        .expect("获取 path 失败")
-        .split(";")
-        .find(|path| {
-          if path.contains("nodejs") {
-            return true;
-          }
-          false
-        })
-        .unwrap()
-        .to_string();
+      .map_err(|err| ErrorKind::Registry(err.to_string()))?;
+    Ok(json.version)
+  }
+
+  /**
+   * 获取 registry 上所有的 dist-tag，tag -> version
+   */
+  pub fn get_package_dist_tags(&self, name: &str) -> crate::Result<HashMap<String, String>> {
+    self.backend.ls(name)
+  }
+
+  /**
+   * 把 from 指向的版本提升为 to，比如 next -> latest
+   */
+  pub fn get_promote_change_list(&self, from: &str, to: &str) -> crate::Result<Vec<DistTagChange>> {
+    let mut change_list = vec![];
+    for package in &self.package_list {
+      let dist_tags = self.get_package_dist_tags(&package.name)?;
+      change_list.extend(promote_change(&package.name, &dist_tags, from, to));
     }
-    self.path.clone()
+    Ok(change_list)
   }
 
-  /* 获取 package.json 中的 version 字段 */
-  pub fn get_pre_package_version(&self) -> Vec<String> {
+  /**
+   * 删除所有包上的某个 dist-tag，latest 不允许删除
+   */
+  pub fn get_remove_change_list(&self, tag: &str) -> crate::Result<Vec<DistTagChange>> {
+    if tag == "latest" {
+      return Err(ErrorKind::Npm(t!("npm.remove_latest")).into());
+    }
+    let mut change_list = vec![];
+    for package in &self.package_list {
+      let current = self.get_package_dist_tags(&package.name)?.get(tag).cloned();
+      if current.is_none() {
+        info!("{}", t!("npm.tag_absent", package.name, tag));
+        continue;
+      }
+      change_list.push(DistTagChange {
+        action: DistTagAction::Rm {
+          name: package.name.clone(),
+          tag: tag.to_string(),
+        },
+        current,
+      });
+    }
+    Ok(change_list)
+  }
+
+  /**
+   * 把 latest 回滚到上一个正式版本，上一个版本根据 git tag 计算
+   */
+  pub fn get_rollback_change_list(&self) -> crate::Result<Vec<DistTagChange>> {
+    let mut change_list = vec![];
+    for package in &self.package_list {
+      let current = self.get_package_latest_version(&package.name)?;
+      let current_version = match Version::parse(&current) {
+        Ok(version) => version,
+        Err(_) => continue,
+      };
+      let pre_version = previous_stable(
+        &current_version,
+        self
+          .get_sort_tags(&package.name)
+          .into_iter()
+          .map(|(_, version)| version),
+      );
+
+      match pre_version {
+        Some(version) => change_list.push(DistTagChange {
+          action: DistTagAction::Add {
+            name: package.name.clone(),
+            version: version.to_string(),
+            tag: "latest".to_string(),
+          },
+          current: Some(current),
+        }),
+        None => warn!("{}", t!("npm.no_previous", package.name, current)),
+      }
+    }
+    Ok(change_list)
+  }
+
+  /* 确认之后执行 dist-tag 变更 */
+  pub fn apply_change_list(&self, change_list: Vec<DistTagChange>) -> crate::Result<()> {
+    if change_list.is_empty() {
+      info!("{}", t!("npm.no_changes"));
+      return Ok(());
+    }
+    print_change_list(&change_list);
+    let mut otp_provider = OtpProvider::new(self.otp_options.clone());
+    if !otp_provider.confirm(&t!("npm.confirm"))? {
+      info!("{}", t!("npm.cancelled"));
+      return Ok(());
+    }
+    let action_list = change_list
+      .into_iter()
+      .map(|change| change.action)
+      .collect();
+    run_dist_tag_list(action_list, self.backend.as_ref(), &mut otp_provider)
+  }
+
+  /* 提升 dist-tag，比如 next -> latest */
+  pub fn promote(&self, from: &str, to: &str) -> crate::Result<()> {
+    self.apply_change_list(self.get_promote_change_list(from, to)?)
+  }
+
+  /* 删除 dist-tag */
+  pub fn remove_tag(&self, tag: &str) -> crate::Result<()> {
+    self.apply_change_list(self.get_remove_change_list(tag)?)
+  }
+
+  /* latest 回滚到上一个版本 */
+  pub fn rollback_latest(&self) -> crate::Result<()> {
+    self.apply_change_list(self.get_rollback_change_list()?)
+  }
+
+  /* 设置 otp 的来源，会用环境变量补全 */
+  pub fn with_otp_options(mut self, otp_options: OtpOptions) -> Npm {
+    self.otp_options = otp_options.with_env();
+    self
+  }
+
+  /* 设置 dist-tag 的执行方式 */
+  pub fn with_backend(mut self, kind: BackendKind) -> Npm {
+    if kind == BackendKind::Registry {
+      match RegistryBackend::new(
+        self.client.clone(),
+        &NpmRc::load(&self.path),
+        &self.registry,
+      ) {
+        Some(backend) => self.backend = Box::new(backend),
+        None => warn!("{}", t!("npm.no_token", self.registry)),
+      }
+    }
+    self
+  }
+
+  /* 设置 git tag 的命名方式 */
+  pub fn with_tag_pattern(mut self, tag_pattern: TagPattern) -> Npm {
+    self.tag_pattern = tag_pattern;
+    self
+  }
+
+  /* 设置 registry，会覆盖 .npmrc 中的配置 */
+  pub fn with_registry(mut self, registry: &str) -> Npm {
+    self.registry = registry::with_trailing_slash(registry);
+    self
+  }
+
+  /* 设置发布前检查的规则级别 */
+  pub fn with_rule_levels(mut self, rule_levels: HashMap<String, RuleLevel>) -> Npm {
+    self.rule_levels = rule_levels;
+    self
+  }
+
+  /* 获取一个包所有的 tag，按照版本号从大到小排序 */
+  fn get_sort_tags(&self, package_name: &str) -> Vec<(String, Version)> {
     let repo = Repository::open(&self.path).unwrap();
     let mut tag_list = repo
       .tag_names(None)
       .unwrap()
       .iter()
+      .flatten()
       .filter_map(|tag| {
-        Version::parse(&get_version(tag.unwrap()).to_owned().version)
-          .ok()
-          .map(|version| (tag.unwrap().to_string(), version))
+        self
+          .tag_pattern
+          .version(tag, package_name)
+          .map(|version| (tag.to_string(), version))
       })
       .collect::<Vec<_>>();
 
     tag_list.sort_by(|(_, a), (_, b)| b.cmp(a));
+    tag_list
+  }
 
-    let sort_tags = tag_list
-      .into_iter()
-      .map(|(tag, _)| -> String { tag })
-      .collect::<Vec<String>>();
-
+  /* 获取每个包上一个版本的 tag，tag 不足两个的包会被跳过 */
+  pub fn get_pre_package_version(&self) -> Vec<String> {
     let pre_package_version = self
       .package_list
       .iter()
-      .map(|package| -> String {
-        let package_name = package.name.as_str();
-        let tag = sort_tags
-          .clone()
-          .into_iter()
-          .filter(|tag| tag.contains(package_name))
-          .collect::<Vec<_>>()
-          .get(1)
-          .unwrap()
-          .clone();
-        tag
+      .filter_map(|package| {
+        let (tag, _) = self.get_sort_tags(&package.name).into_iter().nth(1)?;
+        Some(tag)
       })
       .collect();
 
@@ -242,42 +481,171 @@ impl Npm {
   }
   pub fn new(path: String) -> Npm {
     let client = Client::new();
-    let packages_path = format!("{path}/packages/", path = path);
-    let package_list: Vec<NpmPackageInfo> = fs::read_dir(&packages_path)
-      .unwrap()
-      .filter(|entry| {
-        let entry = entry.as_ref().unwrap();
-        let path = entry.path();
-        let path = path.to_str().unwrap();
-        Path::new(path).is_dir()
-      })
-      .map(|entry| {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        let path = path.to_str().unwrap();
-
-        let data = fs::read_to_string(format!("{path}/package.json", path = path))
-          .expect(format!("{path}/package.json", path = path).as_str());
+    let package_list = find_packages(&path);
 
-        let package_info: NpmPackageInfo =
-          serde_json::from_str(&data).expect("格式化  package.json失败 ");
-
-        return package_info;
-      })
-      .collect();
-
-    println!("🔍 发现了{} 个 包 ->", &package_list.len());
-    println!("-------------------");
+    info!("{}", t!("npm.found_packages", package_list.len()));
+    info!("-------------------");
     for package in &package_list {
-      println!("📦 {}@{}", package.name, package.version)
+      info!("📦 {}@{}", package.name, package.version)
     }
 
-    println!("🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚");
+    info!("🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚");
+
+    let registry = NpmRc::load(&path).registry();
+    let backend = Box::new(NpmCliBackend::new(&path));
 
     Npm {
       path,
       client,
+      registry,
       package_list,
+      otp_options: OtpOptions::default().with_env(),
+      backend,
+      tag_pattern: TagPattern::default(),
+      rule_levels: HashMap::new(),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use semver::Version;
+  use std::collections::HashMap;
+  use std::fs;
+  use tempfile::TempDir;
+
+  use super::{previous_stable, promote_change, DistTagAction, DistTagBackend, Npm};
+  use crate::changelog::git::tests::TestRepo;
+  use crate::i18n::t;
+  use crate::ErrorKind;
+
+  /* 所有请求都失败的 registry */
+  struct FailingBackend;
+
+  impl DistTagBackend for FailingBackend {
+    fn name(&self) -> &str {
+      "failing"
+    }
+
+    fn ls(&self, _name: &str) -> crate::Result<HashMap<String, String>> {
+      Err(ErrorKind::Registry("401 Unauthorized".to_string()).into())
+    }
+
+    fn add(
+      &self,
+      _name: &str,
+      _version: &str,
+      _tag: &str,
+      _otp: Option<&str>,
+    ) -> crate::Result<()> {
+      unreachable!()
+    }
+
+    fn rm(&self, _name: &str, _tag: &str, _otp: Option<&str>) -> crate::Result<()> {
+      unreachable!()
+    }
+  }
+
+  fn dist_tags(tags: &[(&str, &str)]) -> HashMap<String, String> {
+    tags
+      .iter()
+      .map(|(tag, version)| (tag.to_string(), version.to_string()))
+      .collect()
+  }
+
+  fn versions(versions: &[&str]) -> Vec<Version> {
+    versions
+      .iter()
+      .map(|version| Version::parse(version).unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn it_promotes_dist_tags() {
+    let tags = dist_tags(&[("latest", "1.0.0"), ("next", "1.1.0-beta.1")]);
+    let change = promote_change("@ant-design/pro-form", &tags, "next", "latest").unwrap();
+    match change.action {
+      DistTagAction::Add { name, version, tag } => {
+        assert_eq!(name, "@ant-design/pro-form");
+        assert_eq!(version, "1.1.0-beta.1");
+        assert_eq!(tag, "latest");
+      }
+      action => panic!("unexpected action {}", action),
+    }
+    assert_eq!(change.current.as_deref(), Some("1.0.0"));
+
+    let tags = dist_tags(&[("latest", "1.1.0"), ("next", "1.1.0")]);
+    assert!(promote_change("@ant-design/pro-form", &tags, "next", "latest").is_none());
+    assert!(promote_change("@ant-design/pro-form", &tags, "beta", "latest").is_none());
+  }
+
+  #[test]
+  fn it_rolls_back_to_the_previous_stable_version() {
+    let sorted = versions(&["2.0.0", "1.2.0-beta.1", "1.1.0", "1.0.0"]);
+    assert_eq!(
+      previous_stable(&Version::parse("2.0.0").unwrap(), sorted.clone()),
+      Some(Version::parse("1.1.0").unwrap())
+    );
+    assert_eq!(
+      previous_stable(&Version::parse("1.0.0").unwrap(), sorted),
+      None
+    );
+  }
+
+  #[test]
+  fn it_refuses_to_remove_latest() {
+    let dir = TempDir::new().unwrap();
+    let npm = Npm::new(dir.path().display().to_string());
+    let err = npm.get_remove_change_list("latest").unwrap_err();
+    assert_eq!(err.to_string(), t!("error.npm", t!("npm.remove_latest")));
+  }
+
+  #[test]
+  fn it_returns_dist_tag_failures() {
+    let dir = TempDir::new().unwrap();
+    let package_dir = dir.path().join("packages/form");
+    fs::create_dir_all(&package_dir).unwrap();
+    fs::write(
+      package_dir.join("package.json"),
+      r#"{ "name": "@ant-design/pro-form", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+    let mut npm = Npm::new(dir.path().display().to_string());
+    npm.backend = Box::new(FailingBackend);
+    let message = t!("error.registry", "401 Unauthorized");
+    assert_eq!(
+      npm.promote("next", "latest").unwrap_err().to_string(),
+      message
+    );
+    assert_eq!(npm.remove_tag("beta").unwrap_err().to_string(), message);
+  }
+
+  #[test]
+  fn it_skips_packages_without_a_previous_tag() {
+    let mut repo = TestRepo::new();
+    for (dir, name) in [
+      ("form", "@ant-design/pro-form"),
+      ("utils", "@ant-design/pro-utils"),
+    ] {
+      let package_dir = std::path::Path::new(&repo.path())
+        .join("packages")
+        .join(dir);
+      fs::create_dir_all(&package_dir).unwrap();
+      fs::write(
+        package_dir.join("package.json"),
+        format!(r#"{{ "name": "{}", "version": "1.1.0" }}"#, name),
+      )
+      .unwrap();
+    }
+    let first = repo.commit(&[], "feat: init");
+    let second = repo.commit(&[first], "feat: form");
+    repo.tag(first, "@ant-design/pro-form@1.0.0");
+    repo.tag(second, "@ant-design/pro-form@1.1.0");
+    repo.tag(second, "@ant-design/pro-utils@1.1.0");
+    let npm = Npm::new(repo.path());
+    assert_eq!(
+      npm.get_pre_package_version(),
+      vec!["@ant-design/pro-form@1.0.0".to_string()]
+    );
+  }
+}
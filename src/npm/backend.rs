@@ -0,0 +1,176 @@
+use log::info;
+use std::collections::HashMap;
+use std::env::{self, consts::OS};
+use std::process::Command;
+
+use super::otp::is_otp_error;
+use super::DistTagAction;
+use crate::i18n::t;
+use crate::ErrorKind;
+
+#[cfg(windows)]
+pub const NPM: &str = "npm.cmd";
+
+#[cfg(not(windows))]
+pub const NPM: &str = "npm";
+
+/// dist-tag 的读写方式，可以是 npm 命令，也可以直接请求 registry
+pub trait DistTagBackend {
+  /// 后端的名字，用于输出
+  fn name(&self) -> &str;
+
+  /// 获取所有的 dist-tag，tag -> version
+  fn ls(&self, name: &str) -> crate::Result<HashMap<String, String>>;
+
+  /// 把 tag 指向 name@version
+  fn add(&self, name: &str, version: &str, tag: &str, otp: Option<&str>) -> crate::Result<()>;
+
+  /// 删除 tag
+  fn rm(&self, name: &str, tag: &str, otp: Option<&str>) -> crate::Result<()>;
+
+  /// 执行一次 dist-tag 操作，otp 过期或者缺失时返回 [`ErrorKind::Otp`]
+  fn run(&self, action: &DistTagAction, otp: Option<&str>) -> crate::Result<()> {
+    match action {
+      DistTagAction::Add { name, version, tag } => self.add(name, version, tag, otp),
+      DistTagAction::Rm { name, tag } => self.rm(name, tag, otp),
+    }
+  }
+}
+
+/// 调用 npm dist-tag 命令
+pub struct NpmCliBackend {
+  path: String,
+}
+
+impl NpmCliBackend {
+  pub fn new(path: &str) -> NpmCliBackend {
+    NpmCliBackend {
+      path: path.to_string(),
+    }
+  }
+
+  fn exec(&self, args: &[&str], otp: Option<&str>) -> crate::Result<String> {
+    let mut command = Command::new(NPM);
+    if let Some(otp) = otp {
+      command.env("NPM_CONFIG_OTP", otp);
+    }
+    let output = command
+      .current_dir(get_npm_path(&self.path)?)
+      .args(args)
+      .output()
+      .map_err(|err| ErrorKind::Npm(t!("npm.exec_failed", NPM, err)))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if output.status.success() {
+      return Ok(stdout);
+    }
+    if is_otp_error(&stderr) {
+      return Err(ErrorKind::Otp(stderr.trim().to_string()).into());
+    }
+    Err(ErrorKind::Npm(stderr.trim().to_string()).into())
+  }
+}
+
+impl DistTagBackend for NpmCliBackend {
+  fn name(&self) -> &str {
+    "npm"
+  }
+
+  fn ls(&self, name: &str) -> crate::Result<HashMap<String, String>> {
+    // 输出的格式是 latest: 1.0.0
+    let stdout = self.exec(&["dist-tag", "ls", name], None)?;
+    Ok(
+      stdout
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(tag, version)| (tag.trim().to_string(), version.trim().to_string()))
+        .collect(),
+    )
+  }
+
+  fn add(&self, name: &str, version: &str, tag: &str, otp: Option<&str>) -> crate::Result<()> {
+    let spec = format!("{name}@{version}");
+    let stdout = self.exec(&["dist-tag", "add", &spec, tag], otp)?;
+    info!("{}", stdout.trim());
+    Ok(())
+  }
+
+  fn rm(&self, name: &str, tag: &str, otp: Option<&str>) -> crate::Result<()> {
+    let stdout = self.exec(&["dist-tag", "rm", name, tag], otp)?;
+    info!("{}", stdout.trim());
+    Ok(())
+  }
+}
+
+/* 执行 npm 的目录，windows 上是 nodejs 的安装路径 */
+fn get_npm_path(path: &str) -> crate::Result<String> {
+  if OS == "windows" {
+    let path_var = env::var("path").map_err(|_| ErrorKind::Npm(t!("npm.path_failed")))?;
+    return find_nodejs_dir(&path_var);
+  }
+  Ok(path.to_string())
+}
+
+/* 在 path 环境变量中找到 nodejs 的目录 */
+fn find_nodejs_dir(path_var: &str) -> crate::Result<String> {
+  path_var
+    .split(';')
+    .find(|path| path.contains("nodejs"))
+    .map(str::to_string)
+    .ok_or_else(|| ErrorKind::Npm(t!("npm.nodejs_missing")).into())
+}
+
+#[cfg(test)]
+mod tests {
+  use reqwest::Client;
+  use std::env;
+
+  use super::find_nodejs_dir;
+  use crate::npm::registry::{NpmRc, RegistryBackend};
+  use crate::npm::BackendKind;
+
+  #[test]
+  fn it_parses_backend_kinds() {
+    assert_eq!("npm".parse::<BackendKind>().unwrap(), BackendKind::Npm);
+    assert_eq!(
+      "registry".parse::<BackendKind>().unwrap(),
+      BackendKind::Registry
+    );
+    assert!("yarn".parse::<BackendKind>().is_err());
+  }
+
+  #[test]
+  fn it_selects_registry_urls_and_tokens() {
+    let mut npmrc = NpmRc::default();
+    npmrc.parse(
+      "//registry.example.com/:_authToken=registry-token\n\
+       //registry.example.com/npm/:_authToken=scoped-token\n",
+    );
+
+    let backend =
+      RegistryBackend::new(Client::new(), &npmrc, "https://registry.example.com/npm/").unwrap();
+    assert_eq!(backend.token(), "scoped-token");
+    assert_eq!(
+      backend.dist_tags_url("@ant-design/pro-form"),
+      "https://registry.example.com/npm/-/package/@ant-design%2fpro-form/dist-tags"
+    );
+    let backend =
+      RegistryBackend::new(Client::new(), &npmrc, "http://registry.example.com/").unwrap();
+    assert_eq!(backend.token(), "registry-token");
+
+    if env::var("NPM_TOKEN").is_err() && env::var("NODE_AUTH_TOKEN").is_err() {
+      assert!(RegistryBackend::new(Client::new(), &npmrc, "https://registry.npmjs.org/").is_none());
+    }
+  }
+
+  #[test]
+  fn it_finds_the_nodejs_dir() {
+    assert_eq!(
+      find_nodejs_dir(r"C:\Windows;C:\Program Files\nodejs\;C:\Users\me").unwrap(),
+      r"C:\Program Files\nodejs\"
+    );
+    assert!(find_nodejs_dir(r"C:\Windows").is_err());
+  }
+}
@@ -0,0 +1,213 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::i18n::t;
+use crate::ErrorKind;
+
+/// TOTP 的时间窗口，npm 和常见的验证器都是 30 秒
+const TOTP_STEP: u64 = 30;
+
+/// 生成的 otp 位数
+const TOTP_DIGITS: u32 = 6;
+
+/// otp 从哪里来
+#[derive(Debug, Clone, Default)]
+pub struct OtpOptions {
+  /// 直接传入的 otp
+  pub otp: Option<String>,
+  /// base32 格式的 TOTP 密钥，用来自动生成 otp
+  pub totp_secret: Option<String>,
+  /// 非交互模式，拿不到 otp 或者需要确认时直接失败，不会读取 stdin
+  pub non_interactive: bool,
+  /// 跳过 dist-tag 变更的确认
+  pub yes: bool,
+}
+
+impl OtpOptions {
+  /**
+   * 用环境变量补全没有传入的配置
+   * DOCTOR_NPM_OTP / NPM_CONFIG_OTP，DOCTOR_NPM_TOTP_SECRET，CI
+   */
+  pub fn with_env(mut self) -> OtpOptions {
+    if self.otp.is_none() {
+      self.otp = env::var("DOCTOR_NPM_OTP")
+        .or_else(|_| env::var("NPM_CONFIG_OTP"))
+        .ok();
+    }
+    if self.totp_secret.is_none() {
+      self.totp_secret = env::var("DOCTOR_NPM_TOTP_SECRET").ok();
+    }
+    let is_ci = env::var("CI")
+      .map(|ci| ci != "false" && ci != "0")
+      .unwrap_or(false);
+    self.non_interactive = self.non_interactive || is_ci || !io::stdin().is_terminal();
+    self
+  }
+}
+
+/// 负责给 npm 提供 otp，过期之后可以重新获取
+pub struct OtpProvider {
+  options: OtpOptions,
+  current: Option<String>,
+}
+
+impl OtpProvider {
+  pub fn new(options: OtpOptions) -> OtpProvider {
+    OtpProvider {
+      options,
+      current: None,
+    }
+  }
+
+  /**
+   * 获取 otp，没有配置 otp 的时候返回 None，npm 会使用 token 的权限直接执行
+   */
+  pub fn get(&mut self) -> crate::Result<Option<String>> {
+    if self.current.is_some() {
+      return Ok(self.current.clone());
+    }
+    let otp = if let Some(otp) = self.options.otp.clone() {
+      normalize_otp(&otp)?
+    } else if let Some(secret) = self.options.totp_secret.clone() {
+      Some(generate_totp(&secret, unix_time())?)
+    } else if self.options.non_interactive {
+      None
+    } else {
+      prompt_otp(&t!("otp.prompt"))?
+    };
+    self.current = otp;
+    Ok(self.current.clone())
+  }
+
+  /**
+   * npm 提示 otp 过期或者错误之后，重新获取一个
+   * 传入的 otp 不会变化，所以只能失败
+   */
+  pub fn refresh(&mut self) -> crate::Result<Option<String>> {
+    let expired = self.current.take();
+    if let Some(secret) = self.options.totp_secret.clone() {
+      let mut otp = generate_totp(&secret, unix_time())?;
+      // 还在同一个时间窗口里，等到下一个窗口再生成
+      if Some(&otp) == expired.as_ref() {
+        thread::sleep(Duration::from_secs(TOTP_STEP - unix_time() % TOTP_STEP + 1));
+        otp = generate_totp(&secret, unix_time())?;
+      }
+      self.current = Some(otp);
+      return Ok(self.current.clone());
+    }
+    if self.options.non_interactive {
+      return Err(ErrorKind::Otp(t!("otp.expired")).into());
+    }
+    self.current = prompt_otp(&t!("otp.reprompt"))?;
+    Ok(self.current.clone())
+  }
+
+  /**
+   * 等待用户确认，非交互模式下必须传入 yes
+   */
+  pub fn confirm(&self, message: &str) -> crate::Result<bool> {
+    if self.options.yes {
+      return Ok(true);
+    }
+    if self.options.non_interactive {
+      return Err(ErrorKind::Otp(t!("otp.confirm_required")).into());
+    }
+    println!("{}", message);
+    let input = read_line()?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+  }
+}
+
+fn read_line() -> crate::Result<String> {
+  let mut input = String::new();
+  io::stdin().read_line(&mut input)?;
+  Ok(input)
+}
+
+fn prompt_otp(message: &str) -> crate::Result<Option<String>> {
+  println!("{}", message);
+  normalize_otp(&read_line()?)
+}
+
+fn unix_time() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or(0)
+}
+
+/**
+ * 去掉空白和换行，空字符串表示没有 otp，其他情况必须是 6 到 8 位数字
+ */
+pub fn normalize_otp(input: &str) -> crate::Result<Option<String>> {
+  let otp = input.trim();
+  if otp.is_empty() {
+    return Ok(None);
+  }
+  if !(6..=8).contains(&otp.len()) || !otp.chars().all(|c| c.is_ascii_digit()) {
+    return Err(ErrorKind::Otp(t!("otp.invalid", otp)).into());
+  }
+  Ok(Some(otp.to_string()))
+}
+
+/**
+ * 根据 RFC 6238 生成 TOTP
+ */
+pub fn generate_totp(secret: &str, timestamp: u64) -> crate::Result<String> {
+  let secret = secret.trim().replace(' ', "").to_uppercase();
+  let key = base32::decode(base32::Alphabet::RFC4648 { padding: false }, &secret)
+    .ok_or_else(|| ErrorKind::Otp(t!("otp.secret_invalid")))?;
+
+  let mut mac =
+    Hmac::<Sha1>::new_from_slice(&key).map_err(|_| ErrorKind::Otp(t!("otp.secret_empty")))?;
+  mac.update(&(timestamp / TOTP_STEP).to_be_bytes());
+  let hash = mac.finalize().into_bytes();
+
+  let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+  let code = u32::from_be_bytes([
+    hash[offset] & 0x7f,
+    hash[offset + 1],
+    hash[offset + 2],
+    hash[offset + 3],
+  ]);
+
+  Ok(format!(
+    "{:0width$}",
+    code % 10u32.pow(TOTP_DIGITS),
+    width = TOTP_DIGITS as usize
+  ))
+}
+
+/**
+ * npm 的输出里面是不是 otp 过期或者错误
+ */
+pub fn is_otp_error(output: &str) -> bool {
+  output.contains("EOTP") || output.contains("one-time password")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{generate_totp, normalize_otp};
+
+  #[test]
+  fn it_generate_totp() {
+    // RFC 6238 的测试向量，密钥是 "12345678901234567890"
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!(generate_totp(secret, 59).unwrap(), "287082");
+    assert_eq!(generate_totp(secret, 1111111109).unwrap(), "081804");
+  }
+
+  #[test]
+  fn it_normalize_otp() {
+    assert_eq!(
+      normalize_otp("123456\n").unwrap(),
+      Some("123456".to_string())
+    );
+    assert_eq!(normalize_otp(" \r\n").unwrap(), None);
+    assert!(normalize_otp("12a456").is_err());
+  }
+}
@@ -0,0 +1,589 @@
+use annotate_snippets::{
+  display_list::{DisplayList, FormatOptions},
+  snippet::{Annotation, AnnotationType, Slice, Snippet, SourceAnnotation},
+};
+use glob::glob;
+use log::{error, info, log, Level};
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Npm, NpmPackageInfo};
+use crate::i18n::t;
+use crate::logger::{self, LogFormat};
+use crate::ErrorKind;
+
+/// 所有的检查规则
+pub const RULES: [&str; 5] = [
+  "version-exists",
+  "version-not-bumped",
+  "missing-entry",
+  "missing-publish-access",
+  "internal-dependency-mismatch",
+];
+
+/// 需要检查的依赖字段，devDependencies 不会被安装，所以不检查
+const DEPENDENCY_FIELDS: [&str; 3] = ["dependencies", "peerDependencies", "optionalDependencies"];
+
+/// 需要检查是否存在的入口字段
+const ENTRY_FIELDS: [&str; 4] = ["main", "module", "types", "typings"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+/// 配置中规则的级别，off 会关闭这个规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleLevel {
+  Off,
+  Warning,
+  Error,
+}
+
+impl FromStr for RuleLevel {
+  type Err = crate::Error;
+
+  fn from_str(s: &str) -> crate::Result<RuleLevel> {
+    match s {
+      "off" => Ok(RuleLevel::Off),
+      "warning" => Ok(RuleLevel::Warning),
+      "error" => Ok(RuleLevel::Error),
+      _ => Err(ErrorKind::Config(t!("preflight.rule_level_invalid", s)).into()),
+    }
+  }
+}
+
+/// 一个包的一个问题
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightIssue {
+  /// 规则的名字，比如 version-exists
+  pub rule: String,
+  pub severity: Severity,
+  pub message: String,
+  /// 出问题的字段在 package.json 中的路径，比如 ["publishConfig", "access"]
+  pub field: Vec<String>,
+}
+
+/// 一个包的检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageReport {
+  pub name: String,
+  pub version: String,
+  pub path: String,
+  pub issues: Vec<PreflightIssue>,
+}
+
+/// 所有包的检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+  pub packages: Vec<PackageReport>,
+}
+
+impl PreflightReport {
+  /// 是否有 error 级别的问题，有的话不应该发布
+  pub fn has_error(&self) -> bool {
+    self
+      .packages
+      .iter()
+      .flat_map(|package| &package.issues)
+      .any(|issue| issue.severity == Severity::Error)
+  }
+}
+
+fn issue(rule: &str, severity: Severity, message: String, field: &[&str]) -> PreflightIssue {
+  PreflightIssue {
+    rule: rule.to_string(),
+    severity,
+    message,
+    field: field.iter().map(|field| field.to_string()).collect(),
+  }
+}
+
+impl Npm {
+  /**
+   * 发布之前检查所有的包是不是可以发布
+   */
+  pub fn preflight(&self) -> PreflightReport {
+    let sibling_versions: HashMap<&str, &str> = self
+      .package_list
+      .iter()
+      .map(|package| (package.name.as_str(), package.version.as_str()))
+      .collect();
+
+    let packages = self
+      .package_list
+      .iter()
+      .map(|package| {
+        info!(
+          "{}",
+          t!("preflight.checking", package.name, package.version)
+        );
+        let package_json = fs::read_to_string(Path::new(&package.path).join("package.json"))
+          .ok()
+          .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+          .unwrap_or(Value::Null);
+
+        let last_tag_version = self
+          .get_sort_tags(&package.name)
+          .into_iter()
+          .next()
+          .map(|(_, version)| version);
+
+        let mut issues = vec![];
+        issues.extend(self.check_version_exists(package));
+        issues.extend(check_version_bumped(package, last_tag_version.as_ref()));
+        issues.extend(check_entry_files(package, &package_json));
+        issues.extend(check_publish_access(package, &package_json));
+        issues.extend(check_internal_dependencies(
+          &package_json,
+          &sibling_versions,
+        ));
+        let issues = issues
+          .into_iter()
+          .filter_map(|issue| self.apply_rule_level(issue))
+          .collect();
+
+        PackageReport {
+          name: package.name.clone(),
+          version: package.version.clone(),
+          path: package.path.clone(),
+          issues,
+        }
+      })
+      .collect();
+
+    PreflightReport { packages }
+  }
+
+  /* 使用配置中规则的级别，关闭的规则不会输出 */
+  fn apply_rule_level(&self, mut issue: PreflightIssue) -> Option<PreflightIssue> {
+    match self.rule_levels.get(&issue.rule) {
+      Some(RuleLevel::Off) => return None,
+      Some(RuleLevel::Warning) => issue.severity = Severity::Warning,
+      Some(RuleLevel::Error) => issue.severity = Severity::Error,
+      None => {}
+    }
+    Some(issue)
+  }
+
+  /* 这个版本在 registry 上是不是已经存在了 */
+  fn check_version_exists(&self, package: &NpmPackageInfo) -> Option<PreflightIssue> {
+    let endpoint = format!(
+      "{registry}{name}/{version}",
+      registry = self.registry,
+      name = package.name,
+      version = package.version
+    );
+    let response = self.client.get(&endpoint).send().ok()?;
+    if !response.status().is_success() {
+      return None;
+    }
+    Some(issue(
+      "version-exists",
+      Severity::Error,
+      t!("preflight.version_exists", package.name, package.version),
+      &["version"],
+    ))
+  }
+}
+
+/* 版本号要比上一个 tag 大 */
+fn check_version_bumped(
+  package: &NpmPackageInfo,
+  last_tag_version: Option<&Version>,
+) -> Option<PreflightIssue> {
+  let last_tag_version = last_tag_version?;
+  let version = Version::parse(&package.version).ok()?;
+  if version > *last_tag_version {
+    return None;
+  }
+  Some(issue(
+    "version-not-bumped",
+    Severity::Error,
+    t!("preflight.version_not_bumped", version, last_tag_version),
+    &["version"],
+  ))
+}
+
+/* main，types 和 files 中的文件都要存在 */
+fn check_entry_files(package: &NpmPackageInfo, package_json: &Value) -> Vec<PreflightIssue> {
+  let dir = Path::new(&package.path);
+  let mut issues = vec![];
+
+  for field in ENTRY_FIELDS {
+    if let Some(entry) = package_json.get(field).and_then(Value::as_str) {
+      if !dir.join(entry).exists() {
+        issues.push(issue(
+          "missing-entry",
+          Severity::Error,
+          t!("preflight.missing_entry", field, entry),
+          &[field],
+        ));
+      }
+    }
+  }
+
+  let files = package_json
+    .get("files")
+    .and_then(Value::as_array)
+    .cloned()
+    .unwrap_or_default();
+  for file in files.iter().filter_map(Value::as_str) {
+    if file.starts_with('!') {
+      continue;
+    }
+    let pattern = dir
+      .join(file.trim_start_matches("./"))
+      .display()
+      .to_string();
+    let matched = glob(&pattern)
+      .map(|mut paths| paths.any(|path| path.is_ok()))
+      .unwrap_or(false);
+    if !matched {
+      issues.push(issue(
+        "missing-entry",
+        Severity::Error,
+        t!("preflight.missing_files", file),
+        &["files"],
+      ));
+    }
+  }
+
+  issues
+}
+
+/* scope 的包默认是私有的，需要配置 publishConfig.access */
+fn check_publish_access(package: &NpmPackageInfo, package_json: &Value) -> Option<PreflightIssue> {
+  if !package.name.starts_with('@') {
+    return None;
+  }
+  if package_json
+    .pointer("/publishConfig/access")
+    .and_then(Value::as_str)
+    .is_some()
+  {
+    return None;
+  }
+  Some(issue(
+    "missing-publish-access",
+    Severity::Warning,
+    t!("preflight.missing_access", package.name),
+    &["publishConfig"],
+  ))
+}
+
+/* 依赖的兄弟包的版本范围需要包含兄弟包当前的版本 */
+fn check_internal_dependencies(
+  package_json: &Value,
+  sibling_versions: &HashMap<&str, &str>,
+) -> Vec<PreflightIssue> {
+  let mut issues = vec![];
+  for field in DEPENDENCY_FIELDS {
+    let dependencies = match package_json.get(field).and_then(Value::as_object) {
+      Some(dependencies) => dependencies,
+      None => continue,
+    };
+    for (name, range) in dependencies {
+      let sibling_version = match sibling_versions.get(name.as_str()) {
+        Some(version) => *version,
+        None => continue,
+      };
+      let range = match range.as_str() {
+        Some(range) => range,
+        None => continue,
+      };
+      if !range_matches(range, sibling_version) {
+        issues.push(issue(
+          "internal-dependency-mismatch",
+          Severity::Error,
+          t!(
+            "preflight.dependency_mismatch",
+            name,
+            range,
+            sibling_version
+          ),
+          &[field, name],
+        ));
+      }
+    }
+  }
+  issues
+}
+
+/* workspace: 协议会在发布的时候被替换，只检查带版本的写法 */
+fn range_matches(range: &str, version: &str) -> bool {
+  let range = range.trim_start_matches("workspace:");
+  if matches!(range, "*" | "^" | "~")
+    || range.starts_with("file:")
+    || range.starts_with("link:")
+    || range.starts_with("npm:")
+  {
+    return true;
+  }
+  match (VersionReq::parse(range), Version::parse(version)) {
+    (Ok(req), Ok(version)) => req.matches(&version),
+    _ => true,
+  }
+}
+
+/* 找到字段在 package.json 中的位置，返回字符的下标 */
+fn find_field_range(source: &str, field: &[String]) -> Option<(usize, usize)> {
+  let mut offset = 0;
+  for key in field {
+    let quoted = format!("\"{}\"", key);
+    offset += source[offset..].find(&quoted)?;
+  }
+  let line_end = source[offset..]
+    .find('\n')
+    .map(|end| offset + end)
+    .unwrap_or(source.len());
+  let line = source[offset..line_end].trim_end().trim_end_matches(',');
+  let start = source[..offset].chars().count();
+  Some((start, start + line.chars().count()))
+}
+
+/**
+ * 打印检查结果，会在 package.json 中标注出问题的字段
+ */
+pub fn print_report(report: &PreflightReport) {
+  for package in &report.packages {
+    if package.issues.is_empty() {
+      info!("🆗 {}@{}", package.name, package.version);
+      continue;
+    }
+    let package_json_path = Path::new(&package.path).join("package.json");
+    let origin = package_json_path.display().to_string();
+    let source = fs::read_to_string(&package_json_path).unwrap_or_default();
+
+    for issue in &package.issues {
+      let (annotation_type, level) = match issue.severity {
+        Severity::Error => (AnnotationType::Error, Level::Error),
+        Severity::Warning => (AnnotationType::Warning, Level::Warn),
+      };
+      let range = find_field_range(&source, &issue.field)
+        .or_else(|| find_field_range(&source, &["name".to_string()]));
+      let slices = match range {
+        Some(range) => vec![Slice {
+          source: &source,
+          line_start: 1,
+          origin: Some(&origin),
+          fold: true,
+          annotations: vec![SourceAnnotation {
+            label: &issue.message,
+            annotation_type,
+            range,
+          }],
+        }],
+        None => vec![],
+      };
+      let snippet = Snippet {
+        title: Some(Annotation {
+          label: Some(&issue.message),
+          id: Some(&issue.rule),
+          annotation_type,
+        }),
+        footer: vec![],
+        slices,
+        opt: FormatOptions {
+          // json 格式的日志中不需要颜色
+          color: logger::format() == LogFormat::Text,
+          ..Default::default()
+        },
+      };
+      log!(level, "{}\n", DisplayList::from(snippet));
+    }
+  }
+
+  if report.has_error() {
+    error!("{}", t!("preflight.failed"));
+  } else {
+    info!("{}", t!("preflight.passed"));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use semver::Version;
+  use serde_json::Value;
+  use std::collections::HashMap;
+  use std::fs;
+  use std::path::Path;
+
+  use super::{
+    check_entry_files, check_internal_dependencies, check_publish_access, check_version_bumped,
+    issue, RuleLevel, Severity,
+  };
+  use crate::npm::workspace::find_packages;
+  use crate::npm::{Npm, NpmPackageInfo};
+
+  fn write_package(dir: &Path, content: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("package.json"), content).unwrap();
+  }
+
+  fn package_json(package: &NpmPackageInfo) -> Value {
+    let data = fs::read_to_string(Path::new(&package.path).join("package.json")).unwrap();
+    serde_json::from_str(&data).unwrap()
+  }
+
+  fn rules(issues: &[super::PreflightIssue]) -> Vec<(&str, Vec<&str>)> {
+    issues
+      .iter()
+      .map(|issue| {
+        (
+          issue.rule.as_str(),
+          issue.field.iter().map(String::as_str).collect(),
+        )
+      })
+      .collect()
+  }
+
+  /* 一个有两个包的 workspace，form 依赖 utils */
+  fn workspace() -> (tempfile::TempDir, Vec<NpmPackageInfo>) {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path();
+    fs::write(
+      path.join("package.json"),
+      r#"{ "private": true, "workspaces": ["packages/*"] }"#,
+    )
+    .unwrap();
+    write_package(
+      &path.join("packages/form"),
+      r#"{
+  "name": "@ant-design/pro-form",
+  "version": "1.1.0",
+  "main": "lib/index.js",
+  "types": "lib/index.d.ts",
+  "files": ["lib", "es", "!lib/**/*.map"],
+  "dependencies": { "@ant-design/pro-utils": "^2.0.0", "react": "^18.0.0" },
+  "peerDependencies": { "@ant-design/pro-utils": "workspace:*" }
+}"#,
+    );
+    fs::create_dir_all(path.join("packages/form/lib")).unwrap();
+    fs::write(path.join("packages/form/lib/index.js"), "").unwrap();
+    write_package(
+      &path.join("packages/utils"),
+      r#"{
+  "name": "@ant-design/pro-utils",
+  "version": "1.0.0",
+  "publishConfig": { "access": "public" }
+}"#,
+    );
+    let package_list = find_packages(path.to_str().unwrap());
+    (root, package_list)
+  }
+
+  #[test]
+  fn it_checks_version_bumped() {
+    let (_root, package_list) = workspace();
+    let form = &package_list[0];
+    assert_eq!(form.name, "@ant-design/pro-form");
+
+    let older = Version::parse("1.0.0").unwrap();
+    let same = Version::parse("1.1.0").unwrap();
+    assert!(check_version_bumped(form, Some(&older)).is_none());
+    assert!(check_version_bumped(form, None).is_none());
+    let issue = check_version_bumped(form, Some(&same)).unwrap();
+    assert_eq!(issue.rule, "version-not-bumped");
+    assert_eq!(issue.severity, Severity::Error);
+  }
+
+  #[test]
+  fn it_checks_entry_files() {
+    let (_root, package_list) = workspace();
+    let form = &package_list[0];
+    let issues = check_entry_files(form, &package_json(form));
+    assert_eq!(
+      rules(&issues),
+      vec![
+        ("missing-entry", vec!["types"]),
+        ("missing-entry", vec!["files"])
+      ]
+    );
+    assert_eq!(
+      issues[1].message,
+      crate::i18n::t!("preflight.missing_files", "es")
+    );
+
+    let utils = &package_list[1];
+    assert!(check_entry_files(utils, &package_json(utils)).is_empty());
+  }
+
+  #[test]
+  fn it_checks_publish_access() {
+    let (_root, package_list) = workspace();
+    let form = &package_list[0];
+    let issue = check_publish_access(form, &package_json(form)).unwrap();
+    assert_eq!(issue.rule, "missing-publish-access");
+    assert_eq!(issue.severity, Severity::Warning);
+
+    let utils = &package_list[1];
+    assert!(check_publish_access(utils, &package_json(utils)).is_none());
+
+    let mut unscoped = form.clone();
+    unscoped.name = "pro-form".to_string();
+    assert!(check_publish_access(&unscoped, &package_json(form)).is_none());
+  }
+
+  #[test]
+  fn it_checks_internal_dependencies() {
+    let (_root, package_list) = workspace();
+    let sibling_versions: HashMap<&str, &str> = package_list
+      .iter()
+      .map(|package| (package.name.as_str(), package.version.as_str()))
+      .collect();
+    let issues = check_internal_dependencies(&package_json(&package_list[0]), &sibling_versions);
+    assert_eq!(
+      rules(&issues),
+      vec![(
+        "internal-dependency-mismatch",
+        vec!["dependencies", "@ant-design/pro-utils"]
+      )]
+    );
+    assert!(
+      check_internal_dependencies(&package_json(&package_list[1]), &sibling_versions).is_empty()
+    );
+  }
+
+  #[test]
+  fn it_applies_rule_levels() {
+    let (root, _) = workspace();
+    let npm = Npm::new(root.path().display().to_string()).with_rule_levels(
+      [
+        ("missing-entry".to_string(), RuleLevel::Off),
+        ("missing-publish-access".to_string(), RuleLevel::Error),
+        ("version-not-bumped".to_string(), RuleLevel::Warning),
+      ]
+      .into_iter()
+      .collect(),
+    );
+    let check = |rule: &str, severity: Severity| {
+      npm
+        .apply_rule_level(issue(rule, severity, String::new(), &[]))
+        .map(|issue| issue.severity)
+    };
+
+    assert_eq!(check("missing-entry", Severity::Error), None);
+    assert_eq!(
+      check("missing-publish-access", Severity::Warning),
+      Some(Severity::Error)
+    );
+    assert_eq!(
+      check("version-not-bumped", Severity::Error),
+      Some(Severity::Warning)
+    );
+    assert_eq!(
+      check("internal-dependency-mismatch", Severity::Error),
+      Some(Severity::Error)
+    );
+    assert!("warning".parse::<RuleLevel>().is_ok());
+    assert!("fatal".parse::<RuleLevel>().is_err());
+  }
+}
@@ -0,0 +1,248 @@
+use log::info;
+use reqwest::{Client, Response, StatusCode};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{env, fs};
+
+use super::backend::DistTagBackend;
+use crate::ErrorKind;
+
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
+
+/// .npmrc 中和 registry 相关的配置
+#[derive(Debug, Clone, Default)]
+pub struct NpmRc {
+  registry: Option<String>,
+  /// //registry.npmjs.org/ -> token
+  auth_tokens: HashMap<String, String>,
+}
+
+impl NpmRc {
+  /**
+   * 按照 npm 的优先级读取，项目的 .npmrc 优先于用户的 .npmrc
+   */
+  pub fn load(repo: &str) -> NpmRc {
+    let mut npmrc = NpmRc::default();
+    let mut path_list = vec![PathBuf::from(repo).join(".npmrc")];
+    if let Ok(user_config) = env::var("NPM_CONFIG_USERCONFIG") {
+      path_list.push(PathBuf::from(user_config));
+    } else if let Ok(home) = env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
+      path_list.push(PathBuf::from(home).join(".npmrc"));
+    }
+    for path in path_list {
+      if let Ok(content) = fs::read_to_string(path) {
+        npmrc.parse(&content);
+      }
+    }
+    npmrc
+  }
+
+  /**
+   * 解析一个 .npmrc 文件，已经存在的配置不会被覆盖
+   */
+  pub fn parse(&mut self, content: &str) {
+    for line in content.lines() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+        continue;
+      }
+      let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim(), expand_env(value.trim())),
+        None => continue,
+      };
+      if key == "registry" {
+        self.registry.get_or_insert(with_trailing_slash(&value));
+      } else if let Some(prefix) = key.strip_suffix(":_authToken") {
+        self
+          .auth_tokens
+          .entry(with_trailing_slash(prefix))
+          .or_insert(value);
+      }
+    }
+  }
+
+  /**
+   * 使用的 registry，NPM_CONFIG_REGISTRY 优先
+   */
+  pub fn registry(&self) -> String {
+    env::var("NPM_CONFIG_REGISTRY")
+      .map(|registry| with_trailing_slash(&registry))
+      .ok()
+      .or_else(|| self.registry.clone())
+      .unwrap_or_else(|| DEFAULT_REGISTRY.to_string())
+  }
+
+  /**
+   * 获取 registry 对应的 token，匹配最长的前缀，找不到的话使用 NPM_TOKEN / NODE_AUTH_TOKEN
+   */
+  pub fn auth_token(&self, registry: &str) -> Option<String> {
+    let registry = registry
+      .trim_start_matches("https:")
+      .trim_start_matches("http:");
+    self
+      .auth_tokens
+      .iter()
+      .filter(|(prefix, _)| registry.starts_with(prefix.as_str()))
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, token)| token.clone())
+      .or_else(|| env::var("NPM_TOKEN").ok())
+      .or_else(|| env::var("NODE_AUTH_TOKEN").ok())
+      .filter(|token| !token.is_empty())
+  }
+}
+
+pub(crate) fn with_trailing_slash(value: &str) -> String {
+  if value.ends_with('/') {
+    return value.to_string();
+  }
+  format!("{}/", value)
+}
+
+/* 替换 ${NPM_TOKEN} 这样的环境变量 */
+fn expand_env(value: &str) -> String {
+  let mut result = String::new();
+  let mut rest = value;
+  while let Some(start) = rest.find("${") {
+    let end = match rest[start..].find('}') {
+      Some(end) => start + end,
+      None => break,
+    };
+    result.push_str(&rest[..start]);
+    result.push_str(&env::var(&rest[start + 2..end]).unwrap_or_default());
+    rest = &rest[end + 1..];
+  }
+  result.push_str(rest);
+  result
+}
+
+/// 直接请求 registry 的 /-/package/<name>/dist-tags 接口
+pub struct RegistryBackend {
+  client: Client,
+  registry: String,
+  token: String,
+}
+
+impl RegistryBackend {
+  /**
+   * 没有 token 的时候返回 None，这时候应该退回到 npm 命令
+   */
+  pub fn new(client: Client, npmrc: &NpmRc, registry: &str) -> Option<RegistryBackend> {
+    let token = npmrc.auth_token(registry)?;
+    Some(RegistryBackend {
+      client,
+      registry: registry.to_string(),
+      token,
+    })
+  }
+
+  /* 使用的 token，按照 registry 从 .npmrc 中选择 */
+  #[cfg(test)]
+  pub(super) fn token(&self) -> &str {
+    &self.token
+  }
+
+  pub(super) fn dist_tags_url(&self, name: &str) -> String {
+    format!(
+      "{registry}-/package/{name}/dist-tags",
+      registry = self.registry,
+      name = name.replace('/', "%2f")
+    )
+  }
+
+  fn check_response(&self, mut response: Response) -> crate::Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+      return Ok(response);
+    }
+    let www_authenticate = response
+      .headers()
+      .get("www-authenticate")
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or("")
+      .to_string();
+    let body = response.text().unwrap_or_default();
+    if status == StatusCode::UNAUTHORIZED
+      && (www_authenticate.contains("OTP") || body.contains("one-time password"))
+    {
+      return Err(ErrorKind::Otp(body).into());
+    }
+    Err(ErrorKind::Registry(format!("{} {}", status, body)).into())
+  }
+}
+
+impl DistTagBackend for RegistryBackend {
+  fn name(&self) -> &str {
+    "registry"
+  }
+
+  fn ls(&self, name: &str) -> crate::Result<HashMap<String, String>> {
+    let response = self
+      .client
+      .get(&self.dist_tags_url(name))
+      .bearer_auth(&self.token)
+      .send()
+      .map_err(|err| ErrorKind::Registry(err.to_string()))?;
+    self
+      .check_response(response)?
+      .json::<HashMap<String, String>>()
+      .map_err(|err| ErrorKind::Registry(err.to_string()).into())
+  }
+
+  fn add(&self, name: &str, version: &str, tag: &str, otp: Option<&str>) -> crate::Result<()> {
+    let mut request = self
+      .client
+      .put(&format!("{}/{}", self.dist_tags_url(name), tag))
+      .bearer_auth(&self.token)
+      .json(&version);
+    if let Some(otp) = otp {
+      request = request.header("npm-otp", otp);
+    }
+    let response = request
+      .send()
+      .map_err(|err| ErrorKind::Registry(err.to_string()))?;
+    self.check_response(response)?;
+    info!("+{}: {}@{}", tag, name, version);
+    Ok(())
+  }
+
+  fn rm(&self, name: &str, tag: &str, otp: Option<&str>) -> crate::Result<()> {
+    let mut request = self
+      .client
+      .delete(&format!("{}/{}", self.dist_tags_url(name), tag))
+      .bearer_auth(&self.token);
+    if let Some(otp) = otp {
+      request = request.header("npm-otp", otp);
+    }
+    let response = request
+      .send()
+      .map_err(|err| ErrorKind::Registry(err.to_string()))?;
+    self.check_response(response)?;
+    info!("-{}: {}", tag, name);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::NpmRc;
+
+  #[test]
+  fn it_parse_npmrc() {
+    let mut npmrc = NpmRc::default();
+    npmrc.parse(
+      "registry=https://registry.example.com\n\
+       //registry.example.com/:_authToken=project-token\n\
+       # //registry.example.com/:_authToken=commented\n",
+    );
+    npmrc.parse("//registry.example.com/:_authToken=user-token\n");
+
+    assert_eq!(
+      npmrc.registry,
+      Some("https://registry.example.com/".to_string())
+    );
+    assert_eq!(
+      npmrc.auth_token("https://registry.example.com/"),
+      Some("project-token".to_string())
+    );
+  }
+}
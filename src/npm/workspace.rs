@@ -0,0 +1,192 @@
+use glob::glob;
+use log::warn;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::NpmPackageInfo;
+use crate::i18n::t;
+
+/// 没有任何 workspace 配置时使用的默认值
+const DEFAULT_PATTERN: &str = "packages/*";
+
+/// package.json 中的 workspaces，yarn 支持数组和对象两种写法
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Workspaces {
+  List(Vec<String>),
+  Object {
+    #[serde(default)]
+    packages: Vec<String>,
+  },
+}
+
+#[derive(Deserialize)]
+struct RootPackageJson {
+  workspaces: Option<Workspaces>,
+}
+
+/// pnpm-workspace.yaml 和 lerna.json 都是 { packages: [] } 的结构
+#[derive(Deserialize)]
+struct PackagesConfig {
+  packages: Option<Vec<String>>,
+}
+
+/**
+ * 读取 package.json#workspaces，pnpm-workspace.yaml 和 lerna.json 中的 glob
+ * 都没有配置时使用 DEFAULT_PATTERN
+ */
+pub fn get_workspace_patterns(path: &str) -> Vec<String> {
+  let root = Path::new(path);
+  let mut pattern_list: Vec<String> = vec![];
+
+  if let Ok(data) = fs::read_to_string(root.join("package.json")) {
+    match serde_json::from_str::<RootPackageJson>(&data).map(|json| json.workspaces) {
+      Ok(Some(Workspaces::List(packages))) | Ok(Some(Workspaces::Object { packages })) => {
+        pattern_list.extend(packages)
+      }
+      Ok(None) => {}
+      Err(err) => warn!("{}", t!("workspace.parse_failed", "package.json", err)),
+    }
+  }
+
+  if let Ok(data) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+    match serde_yaml::from_str::<PackagesConfig>(&data) {
+      Ok(config) => pattern_list.extend(config.packages.unwrap_or_default()),
+      Err(err) => warn!(
+        "{}",
+        t!("workspace.parse_failed", "pnpm-workspace.yaml", err)
+      ),
+    }
+  }
+
+  if let Ok(data) = fs::read_to_string(root.join("lerna.json")) {
+    match serde_json::from_str::<PackagesConfig>(&data) {
+      // lerna 没有配置 packages 时默认就是 packages/*
+      Ok(config) => pattern_list.extend(
+        config
+          .packages
+          .unwrap_or_else(|| vec![DEFAULT_PATTERN.to_string()]),
+      ),
+      Err(err) => warn!("{}", t!("workspace.parse_failed", "lerna.json", err)),
+    }
+  }
+
+  if pattern_list.is_empty() {
+    pattern_list.push(DEFAULT_PATTERN.to_string());
+  }
+  pattern_list
+}
+
+/**
+ * 展开 glob，返回所有包含 package.json 的目录，! 开头的 glob 用来排除目录
+ */
+pub fn find_package_dirs(path: &str) -> Vec<PathBuf> {
+  let root = Path::new(path);
+  let pattern_list = get_workspace_patterns(path);
+
+  let expand = |pattern: &str| -> Vec<PathBuf> {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    let full_pattern = root.join(pattern).display().to_string();
+    match glob(&full_pattern) {
+      Ok(paths) => paths.filter_map(|entry| entry.ok()).collect(),
+      Err(err) => {
+        warn!("{}", t!("workspace.glob_invalid", pattern, err));
+        vec![]
+      }
+    }
+  };
+
+  let exclude_list: BTreeSet<PathBuf> = pattern_list
+    .iter()
+    .filter_map(|pattern| pattern.strip_prefix('!'))
+    .flat_map(&expand)
+    .collect();
+
+  pattern_list
+    .iter()
+    .filter(|pattern| !pattern.starts_with('!'))
+    .flat_map(|pattern| expand(pattern))
+    .filter(|dir| {
+      dir.is_dir()
+        && dir.join("package.json").is_file()
+        && !exclude_list.contains(dir)
+        && !dir.components().any(|c| c.as_os_str() == "node_modules")
+    })
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect()
+}
+
+/**
+ * 找到所有需要发布的包，会跳过 private 的包和无法解析的 package.json
+ */
+pub fn find_packages(path: &str) -> Vec<NpmPackageInfo> {
+  find_package_dirs(path)
+    .into_iter()
+    .filter_map(|dir| {
+      let package_json_path = dir.join("package.json");
+      let data = fs::read_to_string(&package_json_path).ok()?;
+      match serde_json::from_str::<NpmPackageInfo>(&data) {
+        Ok(mut package_info) => {
+          package_info.path = dir.display().to_string();
+          Some(package_info)
+        }
+        Err(err) => {
+          warn!(
+            "{}",
+            t!(
+              "workspace.package_invalid",
+              package_json_path.display(),
+              err
+            )
+          );
+          None
+        }
+      }
+    })
+    .filter(|package_info| !package_info.private)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use std::path::Path;
+
+  use super::find_packages;
+
+  fn write_package(dir: &Path, content: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("package.json"), content).unwrap();
+  }
+
+  #[test]
+  fn it_find_packages() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path();
+    fs::write(
+      path.join("pnpm-workspace.yaml"),
+      "packages:\n  - 'packages/*/*'\n  - '!packages/legacy/*'\n",
+    )
+    .unwrap();
+    write_package(
+      &path.join("packages/pro/form"),
+      r#"{ "name": "@ant-design/pro-form", "version": "1.0.0" }"#,
+    );
+    write_package(
+      &path.join("packages/pro/site"),
+      r#"{ "name": "site", "version": "1.0.0", "private": true }"#,
+    );
+    write_package(
+      &path.join("packages/legacy/table"),
+      r#"{ "name": "legacy-table", "version": "1.0.0" }"#,
+    );
+    fs::create_dir_all(path.join("packages/pro/empty")).unwrap();
+
+    let package_list = find_packages(path.to_str().unwrap());
+    let name_list: Vec<&str> = package_list.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(name_list, vec!["@ant-design/pro-form"]);
+  }
+}